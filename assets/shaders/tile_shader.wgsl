@@ -18,6 +18,10 @@ var<uniform> settings: InkSettings;
 var info_map: texture_2d<f32>;
 @group(1) @binding(2)
 var info_map_sampler: sampler;
+@group(1) @binding(3)
+var flood_map: texture_2d<f32>;
+@group(1) @binding(4)
+var flood_map_sampler: sampler;
 
 
 #import noisy_bevy::prelude
@@ -26,6 +30,14 @@ var info_map_sampler: sampler;
 #import bevy_pbr::mesh_functions
 
 
+// Where a point on the board falls in the info map. Hex boards push their odd rows half a tile
+// along, so the point is pushed back to its column first.
+fn info_uv(world_position: vec4<f32>) -> vec2<f32> {
+    let row = i32(floor(world_position.z + settings.size.z * 0.5 + 0.5));
+    let shift = select(0., settings.size.y, row % 2 == 1);
+    return vec2<f32>((world_position.x - shift) / settings.size.x + 0.5,world_position.z / settings.size.z + 0.5) + 1. / ( 2. * settings.size.xz);
+}
+
 struct Vertex {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
@@ -52,7 +64,7 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     let world_position = mesh_position_local_to_world(model, vec4<f32>(position, 1.0));
 
     
-    let world_uv = vec2<f32>(world_position.x / settings.size.x + 0.5,world_position.z / settings.size.z + 0.5) + 1. / ( 2. * settings.size.xz);
+    let world_uv = info_uv(world_position);
     var target_y : f32 = textureSampleLevel(info_map, info_map_sampler, world_uv, 0.).x * 1.66666666667;
 
     if position.y > -1.5 && descriminator % 5 != 0 {
@@ -108,7 +120,7 @@ fn fragment(
 
     let world_position = in.world_position + vec4<f32>(settings.world_offset_and_wetness.xyz, 0.);
 
-    let world_uv = vec2<f32>(in.world_position.x / settings.size.x + 0.5,in.world_position.z / settings.size.z + 0.5) + 1. / ( 2. * settings.size.xz);
+    let world_uv = info_uv(in.world_position);
     let sample = textureSample(info_map, info_map_sampler, world_uv);
     let wetness = sample.y;
 
@@ -121,6 +133,12 @@ fn fragment(
 
         vertex_color = mix(parchment_dark, vertex_color, wetness);
     }
+
+    let flooding = textureSample(flood_map, flood_map_sampler, world_uv).x;
+    if flooding > 0. && in.world_normal.y > 0.7 {
+        vertex_color = mix(vertex_color, settings.sea_color, flooding * 0.7);
+    }
+
     if sample.z > 0.5 {
         let ratio = clamp((sample.z - 0.5) * 8., 0., settings.size.w);
         vertex_color = mix(vertex_color, vertex_color * settings.blocked_color, ratio);
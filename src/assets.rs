@@ -16,6 +16,16 @@ pub struct CanalManiaAssets {
     pub aqueduct_icon: Handle<Image>,
     #[asset(key = "lock_icon")]
     pub lock_icon: Handle<Image>,
+    #[asset(key = "reservoir_icon")]
+    pub reservoir_icon: Handle<Image>,
+    #[asset(key = "pumping_station_icon")]
+    pub pumping_station_icon: Handle<Image>,
+    #[asset(key = "tunnel_icon")]
+    pub tunnel_icon: Handle<Image>,
+    #[asset(key = "cutting_icon")]
+    pub cutting_icon: Handle<Image>,
+    #[asset(key = "embankment_icon")]
+    pub embankment_icon: Handle<Image>,
     #[asset(key = "demolish_icon")]
     pub demolish_icon: Handle<Image>,
     #[asset(key = "dig_canal_icon")]
@@ -6,8 +6,10 @@ use iyes_loopless::state::NextState;
 use crate::app_state::*;
 
 use crate::assets::CanalManiaAssets;
-use crate::game::level::Level;
+use crate::game::generator::{generate_level, GeneratorSettings};
 use crate::game::level::LevelList;
+use crate::game::level_loader::{LevelError, LevelFile};
+use crate::game::ratings::{BestRatings, CurrentLevelFile, MAX_STARS};
 use crate::ui::*;
 
 pub struct ChooseLevelPlugin;
@@ -15,22 +17,42 @@ pub struct ChooseLevelPlugin;
 impl Plugin for ChooseLevelPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         clear_ui_system_set(app, AppState::ChooseLevel)
+            .init_resource::<RandomSeed>()
             .add_enter_system(AppState::ChooseLevel, display_ui)
             .add_system(load_board)
-            .add_system(button_pressed.run_in_state(AppState::ChooseLevel));
+            .add_system(button_pressed.run_in_state(AppState::ChooseLevel))
+            .add_system(type_seed.run_in_state(AppState::ChooseLevel))
+            .add_system(update_seed.run_in_state(AppState::ChooseLevel));
     }
 }
 
+/// The seed the next random level is generated from - typed in on the choose level screen.
+#[derive(Resource, Debug, Clone, Copy)]
+struct RandomSeed(u32);
+
+impl Default for RandomSeed {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// Seeds are kept short enough to type in, and to share.
+const MAX_SEED: u32 = 99_999;
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 enum ElId {
     Text,
+    Errors,
     LevelButtonContainer,
+    Seed,
 }
 
 fn display_ui(
     mut commands: Commands,
     level_list_asset: Res<Assets<LevelList>>,
     assets: Res<CanalManiaAssets>,
+    seed: Res<RandomSeed>,
+    ratings: Res<BestRatings>,
 ) {
     if let Some(levels) = level_list_asset.get(&assets.level_list) {
         commands
@@ -42,6 +64,7 @@ fn display_ui(
                     .size(100.)
                     .style(FontStyle::Italic)
                     .id(ElId::Text);
+                parent.text("").size(20.).id(ElId::Errors);
 
                 parent
                     .div()
@@ -49,9 +72,21 @@ fn display_ui(
                     .with_children(|parent| {
                         for level in levels.levels.iter() {
                             let file = &level.file;
-                            let name = &level.name;
+                            let name = match ratings.0.get(file) {
+                                Some(stars) => {
+                                    format!("{} ({stars}/{MAX_STARS} Stars)", level.name)
+                                }
+                                None => level.name.clone(),
+                            };
                             parent.button(format!("level:{file}"), name);
                         }
+                        parent.div().horizontal().with_children(|parent| {
+                            parent.button("random", "Random Level");
+                            parent.text("Seed:").size(20.);
+                            parent.text(seed.0.to_string()).size(20.).id(ElId::Seed);
+                            parent.button("seed_down", "-").style(ButtonStyle::Small);
+                            parent.button("seed_up", "+").style(ButtonStyle::Small);
+                        });
                     });
                 parent.div().padding(5.).with_children(|parent| {
                     parent.button("back", "Back").style(ButtonStyle::Small);
@@ -64,56 +99,125 @@ fn button_pressed(
     mut events: EventReader<ButtonClickEvent>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    levels: Res<Assets<Level>>,
+    levels: Res<Assets<LevelFile>>,
     mut texts: Query<(&UiId<ElId>, &mut GameText)>,
-    containers: Query<(Entity, &UiId<ElId>), With<Div>>,
+    mut containers: Query<(&UiId<ElId>, &mut Div)>,
+    mut seed: ResMut<RandomSeed>,
 ) {
     for event in events.iter() {
         if event.0.starts_with("level:") {
+            commands.insert_resource(CurrentLevelFile(Some(event.0.replace("level:", ""))));
             let file = event.0.replace("level:", "levels/");
-            let handle = asset_server.load::<Level, String>(file);
-
-            if let Some(asset) = levels.get(&handle) {
-                commands.insert_resource(asset.clone());
-                commands.insert_resource(NextState(AppState::InGame));
-            } else {
-                for (id, mut text) in texts.iter_mut() {
-                    if id.val() == &ElId::Text {
-                        text.text = "Loading...".into();
-                    }
+            let handle = asset_server.load::<LevelFile, String>(file);
+
+            match levels.get(&handle) {
+                Some(LevelFile(Ok(level))) => {
+                    commands.insert_resource(level.clone());
+                    commands.insert_resource(NextState(AppState::InGame));
                 }
-                for (entity, id) in containers.iter() {
-                    if id.val() == &ElId::LevelButtonContainer {
-                        commands.entity(entity).despawn_recursive();
+                Some(LevelFile(Err(errors))) => {
+                    show_errors(errors, &mut texts, &mut containers);
+                }
+                None => {
+                    for (id, mut text) in texts.iter_mut() {
+                        if id.val() == &ElId::Text {
+                            text.text = "Loading...".into();
+                        }
+                    }
+                    for (id, mut div) in containers.iter_mut() {
+                        if id.val() == &ElId::LevelButtonContainer {
+                            div.hidden = true;
+                        }
                     }
                 }
             }
+        } else if event.0 == "random" {
+            commands.insert_resource(CurrentLevelFile(None));
+            commands.insert_resource(generate_level(seed.0, &GeneratorSettings::default()));
+            commands.insert_resource(NextState(AppState::InGame));
+        } else if event.0 == "seed_up" {
+            seed.0 = (seed.0 + 1).min(MAX_SEED);
+        } else if event.0 == "seed_down" {
+            seed.0 = seed.0.saturating_sub(1);
         } else if event.0 == "back" {
             commands.insert_resource(NextState(AppState::MainMenu));
         }
     }
 }
 
+fn type_seed(mut characters: EventReader<ReceivedCharacter>, mut seed: ResMut<RandomSeed>) {
+    for character in characters.iter() {
+        if let Some(digit) = character.char.to_digit(10) {
+            let typed = seed.0 * 10 + digit;
+            if typed <= MAX_SEED {
+                seed.0 = typed;
+            }
+        } else if character.char == '\u{8}' {
+            seed.0 /= 10;
+        }
+    }
+}
+
+fn update_seed(seed: Res<RandomSeed>, mut texts: Query<(&UiId<ElId>, &mut GameText)>) {
+    if seed.is_changed() {
+        for (id, mut text) in texts.iter_mut() {
+            if id.val() == &ElId::Seed {
+                text.text = seed.0.to_string();
+            }
+        }
+    }
+}
+
+/// Puts the level buttons back as well, so another level can be picked.
+fn show_errors(
+    errors: &[LevelError],
+    texts: &mut Query<(&UiId<ElId>, &mut GameText)>,
+    containers: &mut Query<(&UiId<ElId>, &mut Div)>,
+) {
+    for error in errors.iter() {
+        error!("Invalid level: {error}");
+    }
+    for (id, mut text) in texts.iter_mut() {
+        match id.val() {
+            ElId::Text => {
+                text.text = "Couldn't Load Level".into();
+            }
+            ElId::Errors => {
+                text.text = errors
+                    .iter()
+                    .map(|error| error.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+            }
+            _ => {}
+        }
+    }
+    for (id, mut div) in containers.iter_mut() {
+        if id.val() == &ElId::LevelButtonContainer && div.hidden {
+            div.hidden = false;
+        }
+    }
+}
+
 fn load_board(
-    mut ev_asset: EventReader<AssetEvent<Level>>,
+    mut ev_asset: EventReader<AssetEvent<LevelFile>>,
     mut commands: Commands,
-    levels: Res<Assets<Level>>,
+    levels: Res<Assets<LevelFile>>,
+    mut texts: Query<(&UiId<ElId>, &mut GameText)>,
+    mut containers: Query<(&UiId<ElId>, &mut Div)>,
 ) {
     for ev in ev_asset.iter() {
-        match ev {
-            AssetEvent::Created { handle } => {
-                if let Some(asset) = levels.get(handle) {
-                    commands.insert_resource(asset.clone());
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = ev {
+            match levels.get(handle) {
+                Some(LevelFile(Ok(level))) => {
+                    commands.insert_resource(level.clone());
                     commands.insert_resource(NextState(AppState::InGame));
                 }
-            }
-            AssetEvent::Modified { handle } => {
-                if let Some(asset) = levels.get(handle) {
-                    commands.insert_resource(asset.clone());
-                    commands.insert_resource(NextState(AppState::InGame));
+                Some(LevelFile(Err(errors))) => {
+                    show_errors(errors, &mut texts, &mut containers);
                 }
+                None => {}
             }
-            _ => {}
         }
     }
 }
@@ -1,5 +1,6 @@
 mod board_runtime_assets;
 mod tile;
+mod topology;
 
 use bevy::{
     prelude::*,
@@ -29,6 +30,8 @@ pub use board_runtime_assets::*;
 
 pub use tile::*;
 
+pub use topology::*;
+
 pub struct BoardPlugin;
 
 impl Plugin for BoardPlugin {
@@ -51,39 +54,23 @@ impl Plugin for BoardPlugin {
 pub struct Board {
     pub width: usize,
     pub height: usize,
+    pub topology: GridTopology,
     pub children: HashMap<(usize, usize), Entity>,
 }
 
 impl Board {
-    pub fn neighbour_ids(&self, x: usize, y: usize) -> [Option<(usize, usize)>; 4] {
-        let above = y.checked_sub(1);
-        let left = x.checked_sub(1);
-        let center_x = Some(x);
-        let center_y = Some(y);
-        let below = if y + 1 < self.height {
-            Some(y + 1)
-        } else {
-            None
-        };
-        let right = if x + 1 < self.width {
-            Some(x + 1)
-        } else {
-            None
-        };
-
-        [
-            tile_position(center_x, above),
-            tile_position(left, center_y),
-            tile_position(right, center_y),
-            tile_position(center_x, below),
-        ]
+    pub fn neighbour_ids(&self, x: usize, y: usize) -> Vec<Option<(usize, usize)>> {
+        self.topology.neighbours(x, y, self.width, self.height)
     }
 
-    pub fn neighbours(&self, x: usize, y: usize) -> [Option<Entity>; 4] {
-        self.neighbour_ids(x, y).map(|p| match p {
-            Some(p) => self.children.get(&p).cloned(),
-            None => None,
-        })
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<Option<Entity>> {
+        self.neighbour_ids(x, y)
+            .into_iter()
+            .map(|p| match p {
+                Some(p) => self.children.get(&p).cloned(),
+                None => None,
+            })
+            .collect()
     }
 }
 
@@ -109,6 +96,7 @@ fn build_board(
     let mut board = Board {
         width: level.width,
         height: level.height,
+        topology: level.topology,
         ..Default::default()
     };
 
@@ -117,13 +105,23 @@ fn build_board(
     if let Some(material) = materials.get_mut(&board_assets.tile_base_material) {
         material.settings.world_offset.x = if offset_x { 0.5 } else { 0. };
         material.settings.world_offset.z = if offset_y { 0.5 } else { 0. };
-        material.settings.size = Vec4::new(board.width as f32, 0., board.height as f32, 0.);
+        material.settings.size = Vec4::new(
+            board.width as f32,
+            board.topology.row_shift(1),
+            board.height as f32,
+            0.,
+        );
     }
 
     if let Some(material) = materials.get_mut(&board_assets.decoration_material) {
         material.settings.world_offset.x = if offset_x { 0.5 } else { 0. };
         material.settings.world_offset.z = if offset_y { 0.5 } else { 0. };
-        material.settings.size = Vec4::new(board.width as f32, 0., board.height as f32, 0.);
+        material.settings.size = Vec4::new(
+            board.width as f32,
+            board.topology.row_shift(1),
+            board.height as f32,
+            0.,
+        );
     }
 
     commands
@@ -134,22 +132,7 @@ fn build_board(
         .with_children(|parent| {
             for (x, column) in level.tiles.iter().enumerate() {
                 for (y, row) in column.iter().enumerate() {
-                    let tile = Tile {
-                        x,
-                        y,
-                        z: row.height,
-                        tile_type: row.tile_type,
-                        is_goal: row.is_goal,
-                        contents: row.contents,
-                        wetness: if row.contents == TileContents::River
-                            || row.tile_type == TileType::Sea
-                        {
-                            Wetness::WaterSource
-                        } else {
-                            Wetness::Dry
-                        },
-                        cost_modifier: row.cost_modifier,
-                    };
+                    let tile = row.to_tile(x, y);
                     let source = if tile.wetness == Wetness::WaterSource {
                         WetnessSource::Source(x, y)
                     } else {
@@ -172,6 +155,7 @@ fn build_board(
                     title: level.title.clone(),
                     text: level.initial_description.clone(),
                     continue_button: Some("Play".into()),
+                    choices: vec![],
                 });
                 commands.insert_resource(NextState(GameState::Description));
             }
@@ -179,7 +163,7 @@ fn build_board(
     }
 }
 
-const TILE_HEIGHT: u8 = u8::MAX / 10;
+const TILE_HEIGHT: u8 = u8::MAX / (MAX_HEIGHT as u8 + 1);
 
 fn build_tile(
     mut commands: Commands,
@@ -210,7 +194,8 @@ fn build_tile(
         if let Ok(board) = boards.get_single() {
             let width = board.width;
             let height = board.height;
-            let mut content = vec![(0u8, false, false, false); width * height];
+            let mut content = vec![(0u8, 0u8, false, false); width * height];
+            let mut flooded = vec![u8::MIN; width * height];
 
             for (_, tile, _) in neighbour_tiles.iter() {
                 let x = tile.x;
@@ -221,14 +206,16 @@ fn build_tile(
                 } else {
                     tile.z + 1
                 };
-                let is_wet = matches!(tile.wetness, Wetness::WaterSource | Wetness::Wet(_));
+                // Water running through a tunnel is hidden under the hill
+                let is_wet = matches!(tile.wetness, Wetness::WaterSource | Wetness::Wet(_))
+                    && !matches!(tile.contents, TileContents::Tunnel(_));
 
                 let i = y * width + x;
 
                 if let Some(content) = content.get_mut(i) {
-                    content.0 = height as u8;
+                    content.0 = height.min(u8::MAX as usize) as u8;
                     if is_wet {
-                        content.1 = true;
+                        content.1 = u8::MAX;
                     }
 
                     match tile.cost_modifier {
@@ -241,6 +228,11 @@ fn build_tile(
                         }
                     }
                 }
+                if tile.flooded && !is_wet {
+                    if let Some(flooded) = flooded.get_mut(i) {
+                        *flooded = u8::MAX;
+                    }
+                }
             }
 
             let size = Extent3d {
@@ -251,44 +243,57 @@ fn build_tile(
             let format = TextureFormat::Rgba8Unorm;
             let data = content
                 .iter()
-                .flat_map(|(height, is_wet, blocked, cost_modifier)| {
+                .flat_map(|(height, wetness, blocked, cost_modifier)| {
                     [
-                        *height * TILE_HEIGHT,
-                        if *is_wet { u8::MAX } else { u8::MIN },
+                        height.saturating_mul(TILE_HEIGHT),
+                        *wetness,
                         if *blocked { u8::MAX } else { u8::MIN },
                         if *cost_modifier { u8::MAX } else { u8::MIN },
                     ]
                 })
                 .collect::<Vec<_>>();
+            let sampler = bevy::render::texture::ImageSampler::Descriptor(SamplerDescriptor {
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Linear,
+                ..Default::default()
+            });
             let mut image = Image::new(size, TextureDimension::D2, data, format);
-            image.sampler_descriptor =
-                bevy::render::texture::ImageSampler::Descriptor(SamplerDescriptor {
-                    address_mode_u: AddressMode::ClampToEdge,
-                    address_mode_v: AddressMode::ClampToEdge,
-                    mag_filter: FilterMode::Linear,
-                    min_filter: FilterMode::Linear,
-                    mipmap_filter: FilterMode::Linear,
-                    ..Default::default()
-                });
-
+            image.sampler_descriptor = sampler.clone();
             let result = images.set(board_assets.tile_info_map.clone(), image);
 
+            let mut image = Image::new(size, TextureDimension::D2, flooded, TextureFormat::R8Unorm);
+            image.sampler_descriptor = sampler;
+            let flood_map = images.set(board_assets.tile_flood_map.clone(), image);
+
             let (offset_x, offset_y) = (board.width % 2 == 0, board.height % 2 == 0);
 
             if let Some(material) = materials.get_mut(&board_assets.tile_base_material) {
                 material.settings.world_offset.x = if offset_x { 0.5 } else { 0. };
                 material.settings.world_offset.z = if offset_y { 0.5 } else { 0. };
-                material.settings.size =
-                    Vec4::new(board.width as f32, 0., board.height as f32, 0.9);
+                material.settings.size = Vec4::new(
+                    board.width as f32,
+                    board.topology.row_shift(1),
+                    board.height as f32,
+                    0.9,
+                );
                 material.info_map = result.clone();
+                material.flood_map = flood_map.clone();
             }
 
             if let Some(material) = materials.get_mut(&board_assets.decoration_material) {
                 material.settings.world_offset.x = if offset_x { 0.5 } else { 0. };
                 material.settings.world_offset.z = if offset_y { 0.5 } else { 0. };
-                material.settings.size =
-                    Vec4::new(board.width as f32, 0., board.height as f32, 0.2);
+                material.settings.size = Vec4::new(
+                    board.width as f32,
+                    board.topology.row_shift(1),
+                    board.height as f32,
+                    0.2,
+                );
                 material.info_map = result;
+                material.flood_map = flood_map;
             }
         }
     }
@@ -308,6 +313,10 @@ fn update_tile(
     assets: &CanalManiaAssets,
     primary: bool,
 ) {
+    let topology = boards
+        .get_single()
+        .map(|board| board.topology)
+        .unwrap_or_default();
     let neighbours = if let Some(n) = neighbours {
         n.0.iter()
             .map(|e| {
@@ -320,7 +329,7 @@ fn update_tile(
             .collect::<Vec<_>>()
     } else if let Ok(board) = boards.get_single() {
         let n = board.neighbours(tile.x, tile.y);
-        commands.entity(entity).insert(TileNeighbours(n));
+        commands.entity(entity).insert(TileNeighbours(n.clone()));
         n.iter()
             .map(|e| {
                 if let Some(e) = e {
@@ -331,11 +340,12 @@ fn update_tile(
             })
             .collect::<Vec<_>>()
     } else {
-        (0..8)
+        (0..topology.max_neighbours())
             .map(|_| Option::<(Entity, &Tile, Option<&TileNeighbours>)>::None)
             .collect::<Vec<_>>()
     };
-    let center = Vec3::new(tile.x as f32, (tile.z as f32) / 6., tile.y as f32);
+    let (x, y) = topology.centre(tile.x, tile.y);
+    let center = Vec3::new(x, (tile.z as f32) / 6., y);
     let mut entity = commands.entity(entity);
     let base_material = materials.tile_base_material.clone();
     entity.insert((
@@ -437,6 +447,7 @@ fn update_tile(
                 spawn_content(
                     tile,
                     &neighbours,
+                    &topology.centre_offsets(tile.x, tile.y),
                     assets,
                     parent,
                     materials.decoration_material.clone(),
@@ -464,6 +475,7 @@ fn update_tile(
 fn spawn_content(
     tile: &Tile,
     neighbours: &[Option<(Entity, &Tile, Option<&TileNeighbours>)>],
+    offsets: &[(f32, f32)],
     assets: &CanalManiaAssets,
     parent: &mut ChildBuilder,
     base_material: Handle<TileMaterial>,
@@ -472,86 +484,107 @@ fn spawn_content(
     match tile.contents {
         TileContents::None => {}
         TileContents::Road => {
-            let neighbours = check_neighbours(
-                neighbours,
-                |t| t.contents == TileContents::Road,
-                |t, _, _| t.wetness,
-            );
+            let neighbours =
+                check_neighbours(neighbours, |t| t.carries_road(), |t, _, _| t.wetness);
 
-            let n = neighbours[0];
-            let w = neighbours[1];
-            let e = neighbours[2];
-            let s = neighbours[3];
+            let neighbours = directions(&neighbours, offsets);
 
-            spawn_variant(tile, !is_wet, assets, n, w, e, s, parent, base_material);
+            spawn_variant(tile, !is_wet, assets, &neighbours, parent, base_material);
         }
-        TileContents::Canal => {
+        TileContents::Canal | TileContents::Reservoir | TileContents::Bridge => {
+            let roads = check_neighbours(neighbours, |t| t.carries_road(), |t, _, _| t.wetness);
+            let z = tile.water_level();
             let neighbours = check_neighbours(
                 neighbours,
                 |t| {
-                    matches!(t.contents, TileContents::Canal | TileContents::River)
-                        && tile.z.abs_diff(t.z) < 1
-                        || matches!(t.contents, TileContents::Lock) && tile.z.abs_diff(t.z) < 5
-                        || if let TileContents::Aquaduct(h) = t.contents {
-                            tile.z == h + t.z
-                        } else {
-                            false
-                        }
+                    matches!(
+                        t.contents,
+                        TileContents::Canal
+                            | TileContents::Bridge
+                            | TileContents::River
+                            | TileContents::Reservoir
+                            | TileContents::Aquaduct(_)
+                            | TileContents::Tunnel(_)
+                    ) && z == t.water_level()
+                        || matches!(
+                            t.contents,
+                            TileContents::Lock | TileContents::PumpingStation
+                        ) && z.abs_diff(t.water_level()) < 5
                 },
                 |t, _, _| t.wetness,
             );
 
-            let n = neighbours[0];
-            let w = neighbours[1];
-            let e = neighbours[2];
-            let s = neighbours[3];
+            let neighbours = directions(&neighbours, offsets);
 
-            spawn_variant(tile, !is_wet, assets, n, w, e, s, parent, base_material);
+            if tile.contents == TileContents::Bridge {
+                let canal = Tile {
+                    contents: TileContents::Canal,
+                    ..tile.clone()
+                };
+                let road = Tile {
+                    contents: TileContents::Road,
+                    ..tile.clone()
+                };
+                spawn_variant(
+                    &canal,
+                    !is_wet,
+                    assets,
+                    &neighbours,
+                    parent,
+                    base_material.clone(),
+                );
+                spawn_variant(
+                    &road,
+                    true,
+                    assets,
+                    &directions(&roads, offsets),
+                    parent,
+                    base_material,
+                );
+            } else {
+                spawn_variant(tile, !is_wet, assets, &neighbours, parent, base_material);
+            }
         }
         TileContents::River => {
+            let z = tile.water_level();
             let neighbours = check_neighbours(
                 neighbours,
                 |t| {
-                    matches!(t.contents, TileContents::Canal) && tile.z.abs_diff(t.z) < 2
-                        || matches!(t.contents, TileContents::Lock) && tile.z.abs_diff(t.z) < 5
+                    matches!(t.contents, TileContents::Canal | TileContents::Bridge)
+                        && z.abs_diff(t.water_level()) < 2
+                        || matches!(t.contents, TileContents::Lock)
+                            && z.abs_diff(t.water_level()) < 5
                         || matches!(t.contents, TileContents::River)
-                        || if let TileContents::Aquaduct(h) = t.contents {
-                            tile.z == h + t.z
-                        } else {
-                            false
-                        }
+                        || matches!(t.contents, TileContents::Aquaduct(_)) && z == t.water_level()
                 },
                 |t, _, _| t.wetness,
             );
 
-            let n = neighbours[0];
-            let w = neighbours[1];
-            let e = neighbours[2];
-            let s = neighbours[3];
+            let neighbours = directions(&neighbours, offsets);
 
-            spawn_variant(tile, !is_wet, assets, n, w, e, s, parent, base_material);
+            spawn_variant(tile, !is_wet, assets, &neighbours, parent, base_material);
         }
-        TileContents::Lock => {
+        TileContents::Lock | TileContents::PumpingStation => {
+            let z = tile.water_level();
             let neighbours = check_neighbours(
                 neighbours,
                 |t| {
                     matches!(
                         t.contents,
-                        TileContents::Canal | TileContents::River | TileContents::Lock
-                    ) && tile.z.abs_diff(t.z) < 5
-                        || if let TileContents::Aquaduct(h) = t.contents {
-                            tile.z == h + t.z
-                        } else {
-                            false
-                        }
+                        TileContents::Canal
+                            | TileContents::Bridge
+                            | TileContents::River
+                            | TileContents::Lock
+                            | TileContents::Reservoir
+                            | TileContents::PumpingStation
+                            | TileContents::Tunnel(_)
+                    ) && z.abs_diff(t.water_level()) < 5
+                        || matches!(t.contents, TileContents::Aquaduct(_)) && z == t.water_level()
                 },
                 |t, _, _| t.wetness,
             );
 
-            let n = neighbours[0];
-            let w = neighbours[1];
-            let e = neighbours[2];
-            let s = neighbours[3];
+            let neighbours = directions(&neighbours, offsets);
 
             let tmp = Tile {
                 contents: TileContents::Canal,
@@ -562,39 +595,57 @@ fn spawn_content(
                 &tmp,
                 !is_wet,
                 assets,
-                n,
-                w,
-                e,
-                s,
+                &neighbours,
                 parent,
                 base_material.clone(),
             );
-            spawn_variant(tile, !is_wet, assets, n, w, e, s, parent, base_material);
+            spawn_variant(tile, !is_wet, assets, &neighbours, parent, base_material);
         }
-        TileContents::Aquaduct(h) => {
-            let z = tile.z + h;
+        TileContents::Aquaduct(_) => {
+            let z = tile.water_level();
             let neighbours = check_neighbours(
                 neighbours,
                 |t| {
                     matches!(
                         t.contents,
-                        TileContents::Canal | TileContents::River | TileContents::Lock
-                    ) && z == t.z
-                        || if let TileContents::Aquaduct(h) = t.contents {
-                            z == h + t.z
-                        } else {
-                            false
-                        }
+                        TileContents::Canal
+                            | TileContents::Bridge
+                            | TileContents::River
+                            | TileContents::Lock
+                            | TileContents::Aquaduct(_)
+                            | TileContents::Tunnel(_)
+                    ) && z == t.water_level()
+                },
+                |t, _, _| t.wetness,
+            );
+
+            let neighbours = directions(&neighbours, offsets);
+
+            spawn_variant(tile, !is_wet, assets, &neighbours, parent, base_material);
+        }
+        TileContents::Tunnel(_) => {
+            let z = tile.water_level();
+            let neighbours = check_neighbours(
+                neighbours,
+                |t| {
+                    matches!(
+                        t.contents,
+                        TileContents::Canal
+                            | TileContents::Bridge
+                            | TileContents::River
+                            | TileContents::Lock
+                            | TileContents::Reservoir
+                            | TileContents::PumpingStation
+                            | TileContents::Aquaduct(_)
+                            | TileContents::Tunnel(_)
+                    ) && z == t.water_level()
                 },
                 |t, _, _| t.wetness,
             );
 
-            let n = neighbours[0];
-            let w = neighbours[1];
-            let e = neighbours[2];
-            let s = neighbours[3];
+            let neighbours = directions(&neighbours, offsets);
 
-            spawn_variant(tile, !is_wet, assets, n, w, e, s, parent, base_material);
+            spawn_variant(tile, !is_wet, assets, &neighbours, parent, base_material);
         }
     }
 }
@@ -615,61 +666,77 @@ pub fn check_neighbours<
     neighbours: &[Option<(Entity, &Tile, R)>],
     checked: F,
     selector: S,
-) -> [NeighbourMatch<(TileContents, usize, Z)>; 4] {
-    let mut result = [NeighbourMatch::NoNeighbour; 4];
-
-    #[allow(clippy::needless_range_loop)]
-    for i in 0..8 {
-        if let Some(Some((e, neighbour, v))) = neighbours.get(i) {
-            result[i] = if checked(neighbour) {
-                NeighbourMatch::Matches((
-                    neighbour.contents,
-                    neighbour.z,
-                    selector(neighbour, v, e),
-                ))
-            } else {
-                NeighbourMatch::DoesntMatch
-            };
-        }
-    }
-    result
+) -> Vec<NeighbourMatch<(TileContents, usize, Z)>> {
+    neighbours
+        .iter()
+        .map(|neighbour| match neighbour {
+            Some((e, neighbour, v)) => {
+                if checked(neighbour) {
+                    NeighbourMatch::Matches((
+                        neighbour.contents,
+                        neighbour.z,
+                        selector(neighbour, v, e),
+                    ))
+                } else {
+                    NeighbourMatch::DoesntMatch
+                }
+            }
+            None => NeighbourMatch::NoNeighbour,
+        })
+        .collect()
+}
+
+/// Pairs each neighbour with the angle it lies at - in degrees, with 0 pointing along x - and
+/// how far away its center is, going by where the topology puts the neighbours' centres.
+fn directions<T: Copy>(neighbours: &[T], offsets: &[(f32, f32)]) -> Vec<(T, f32, f32)> {
+    neighbours
+        .iter()
+        .zip(offsets)
+        .map(|(neighbour, (dx, dy))| {
+            let (dx, dy) = (*dx, -dy);
+            (*neighbour, dy.atan2(dx).to_degrees(), dx.hypot(dy))
+        })
+        .collect()
 }
 
 fn spawn_variant<T: Material>(
     tile: &Tile,
     _is_dry: bool,
     assets: &CanalManiaAssets,
-    n: NeighbourMatch<(TileContents, usize, Wetness)>,
-    w: NeighbourMatch<(TileContents, usize, Wetness)>,
-    e: NeighbourMatch<(TileContents, usize, Wetness)>,
-    s: NeighbourMatch<(TileContents, usize, Wetness)>,
+    neighbours: &[(NeighbourMatch<(TileContents, usize, Wetness)>, f32, f32)],
     parent: &mut ChildBuilder,
     material: Handle<T>,
 ) {
     let content_type = tile.contents;
     let height = tile.z;
+    // Canals on embankments or in cuttings sit their water apart from the ground
+    let surface = match tile.water_surface {
+        Some(level) => Vec3::Y * (level as f32 - tile.z as f32) / 6.,
+        None => Vec3::ZERO,
+    };
     let mut num_river_neighbours = 0usize;
-    let results = [(n, 90f32), (w, 180.), (s, 270.), (e, 0.)]
-        .into_iter()
-        .filter_map(|(neighbour, angle)| {
-            if let NeighbourMatch::Matches((content, z, _)) = neighbour {
+    let results = neighbours
+        .iter()
+        .filter_map(|(neighbour, angle, reach)| {
+            let (angle, reach) = (*angle, *reach);
+            if let NeighbourMatch::Matches((content, z, _)) = *neighbour {
                 if content == TileContents::River {
                     num_river_neighbours += 1;
                 }
                 match (content_type, content) {
                     (TileContents::Canal, TileContents::Aquaduct(u)) => {
-                        Some((TileContents::Aquaduct(u), angle, z, false))
+                        Some((TileContents::Aquaduct(u), angle, z, false, reach))
                     }
                     (TileContents::River, TileContents::Aquaduct(u)) => {
-                        Some((TileContents::Aquaduct(u), angle, z, false))
+                        Some((TileContents::Aquaduct(u), angle, z, false, reach))
                     }
                     (TileContents::River, TileContents::Canal) => {
-                        Some((TileContents::River, angle, z, true))
+                        Some((TileContents::River, angle, z, true, reach))
                     }
                     (TileContents::River, TileContents::Lock) => {
-                        Some((TileContents::River, angle, z, true))
+                        Some((TileContents::River, angle, z, true, reach))
                     }
-                    _ => Some((content_type, angle, height, false)),
+                    _ => Some((content_type, angle, height, false, reach)),
                 }
             } else {
                 None
@@ -677,27 +744,25 @@ fn spawn_variant<T: Material>(
         })
         .collect::<Vec<_>>();
 
-    let map_edge = [(n, 90f32), (w, 180.), (s, 270.), (e, 0.)]
-        .into_iter()
-        .filter_map(|(neighbour, angle)| {
+    let map_edge = neighbours
+        .iter()
+        .filter_map(|(neighbour, angle, reach)| {
             if matches!(neighbour, NeighbourMatch::NoNeighbour) {
-                Some(angle)
+                Some((*angle, *reach))
             } else {
                 None
             }
         })
         .next();
 
-    if let Some(angle) = map_edge {
+    if let Some((angle, reach)) = map_edge {
         match content_type {
             TileContents::River => {
                 if num_river_neighbours == 1 {
                     parent.spawn(MaterialMeshBundle {
                         mesh: assets.river_line.clone(),
                         material: material.clone(),
-                        transform: Transform::from_rotation(Quat::from_rotation_y(
-                            angle.to_radians(),
-                        )),
+                        transform: arm_transform(angle, reach),
                         ..Default::default()
                     });
                 }
@@ -706,7 +771,7 @@ fn spawn_variant<T: Material>(
                 parent.spawn(MaterialMeshBundle {
                     mesh: assets.road_line.clone(),
                     material: material.clone(),
-                    transform: Transform::from_rotation(Quat::from_rotation_y(angle.to_radians())),
+                    transform: arm_transform(angle, reach),
                     ..Default::default()
                 });
             }
@@ -718,7 +783,7 @@ fn spawn_variant<T: Material>(
         std::cmp::Ordering::Less => {
             let position = match content_type {
                 TileContents::Aquaduct(u) => Vec3::Y * (u as f32 - 1. + height as f32) / 6.,
-                _ => Vec3::ZERO,
+                _ => surface,
             };
 
             let mesh = content_type.center(assets);
@@ -731,10 +796,10 @@ fn spawn_variant<T: Material>(
             });
         }
         std::cmp::Ordering::Equal => {
-            for (content, rotation, height, transition) in results.iter() {
+            for (content, rotation, height, transition, reach) in results.iter() {
                 let position = match content {
                     TileContents::Aquaduct(u) => Vec3::Y * (*u as f32 - 1. + *height as f32) / 6.,
-                    _ => Vec3::ZERO,
+                    _ => surface,
                 };
 
                 let mesh = match content {
@@ -764,19 +829,16 @@ fn spawn_variant<T: Material>(
                 parent.spawn(MaterialMeshBundle {
                     mesh,
                     material: material.clone(),
-                    transform: Transform::from_rotation(Quat::from_rotation_y(
-                        rotation.to_radians(),
-                    ))
-                    .with_translation(position),
+                    transform: arm_transform(*rotation, *reach).with_translation(position),
                     ..Default::default()
                 });
             }
         }
         std::cmp::Ordering::Greater => {
-            for (content, rotation, height, transition) in results.iter() {
+            for (content, rotation, height, transition, reach) in results.iter() {
                 let position = match content {
                     TileContents::Aquaduct(u) => Vec3::Y * (*u as f32 - 1. + *height as f32) / 6.,
-                    _ => Vec3::ZERO,
+                    _ => surface,
                 };
 
                 let mesh = if !transition {
@@ -788,10 +850,7 @@ fn spawn_variant<T: Material>(
                 parent.spawn(MaterialMeshBundle {
                     mesh,
                     material: material.clone(),
-                    transform: Transform::from_rotation(Quat::from_rotation_y(
-                        rotation.to_radians(),
-                    ))
-                    .with_translation(position),
+                    transform: arm_transform(*rotation, *reach).with_translation(position),
                     ..Default::default()
                 });
             }
@@ -799,6 +858,13 @@ fn spawn_variant<T: Material>(
     }
 }
 
+/// Turns a piece that runs along x to face a neighbour, stretching it to reach further away
+/// neighbours like diagonals.
+fn arm_transform(angle: f32, reach: f32) -> Transform {
+    Transform::from_rotation(Quat::from_rotation_y(angle.to_radians()))
+        .with_scale(Vec3::new(reach, 1., 1.))
+}
+
 #[derive(Clone)]
 pub enum TileEvent {
     Clicked(Tile, Entity),
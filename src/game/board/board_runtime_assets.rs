@@ -1,7 +1,10 @@
 use bevy::{
     prelude::*,
-    reflect::Reflect,
-    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    reflect::{Reflect, TypeUuid},
+    render::{
+        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
 };
 
 use crate::game::tile_shader::{InkSettings, TileMaterial};
@@ -9,6 +12,7 @@ use crate::game::tile_shader::{InkSettings, TileMaterial};
 #[derive(Resource, Reflect)]
 pub struct BoardRuntimeAssets {
     pub tile_info_map: Handle<Image>,
+    pub tile_flood_map: Handle<Image>,
     pub tile_base_material: Handle<TileMaterial>,
     pub decoration_material: Handle<TileMaterial>,
     pub selector: Handle<Mesh>,
@@ -18,6 +22,78 @@ pub struct BoardRuntimeAssets {
     pub selector_selected: Handle<StandardMaterial>,
 }
 
+/// Tunnels don't have models of their own - their pieces are built in `setup_board_materials`.
+/// Each piece runs along x, like the modelled ones.
+pub const TUNNEL_LINE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Mesh::TYPE_UUID, 0x6a1c_93e2_54d0_7b18);
+pub const TUNNEL_END: HandleUntyped =
+    HandleUntyped::weak_from_u64(Mesh::TYPE_UUID, 0x6a1c_93e2_54d0_7b19);
+pub const TUNNEL_CENTER: HandleUntyped =
+    HandleUntyped::weak_from_u64(Mesh::TYPE_UUID, 0x6a1c_93e2_54d0_7b1a);
+
+/// Inked, like the outlines on the modelled pieces.
+const TOWPATH_COLOR: [f32; 4] = [0., 0., 0., 2.];
+const PORTAL_COLOR: [f32; 4] = [0.55, 0.35, 0.25, 1.];
+
+/// Puts boxes of colour together into one mesh.
+fn box_mesh(parts: &[(shape::Box, [f32; 4])]) -> Mesh {
+    let (mut positions, mut normals, mut colors, mut indices) = (vec![], vec![], vec![], vec![]);
+    for (part, color) in parts {
+        let part = Mesh::from(*part);
+        let offset = positions.len() as u32;
+        if let (
+            Some(VertexAttributeValues::Float32x3(part_positions)),
+            Some(VertexAttributeValues::Float32x3(part_normals)),
+        ) = (
+            part.attribute(Mesh::ATTRIBUTE_POSITION),
+            part.attribute(Mesh::ATTRIBUTE_NORMAL),
+        ) {
+            positions.extend_from_slice(part_positions);
+            normals.extend_from_slice(part_normals);
+            colors.extend(part_positions.iter().map(|_| *color));
+        }
+        if let Some(part_indices) = part.indices() {
+            indices.extend(part_indices.iter().map(|index| index as u32 + offset));
+        }
+    }
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// The towpath the horses were led along over the hill, while the boat was legged through below.
+fn towpath(from: f32, to: f32) -> (shape::Box, [f32; 4]) {
+    (
+        shape::Box {
+            min_x: from,
+            max_x: to,
+            min_y: 0.,
+            max_y: 0.02,
+            min_z: -0.04,
+            max_z: 0.04,
+        },
+        TOWPATH_COLOR,
+    )
+}
+
+/// The mouth of the tunnel, facing along -x.
+fn portal() -> (shape::Box, [f32; 4]) {
+    (
+        shape::Box {
+            min_x: -0.1,
+            max_x: 0.,
+            min_y: 0.,
+            max_y: 0.2,
+            min_z: -0.2,
+            max_z: 0.2,
+        },
+        PORTAL_COLOR,
+    )
+}
+
 pub fn setup_board_materials(
     mut commands: Commands,
     mut materials: ResMut<Assets<StandardMaterial>>,
@@ -33,9 +109,16 @@ pub fn setup_board_materials(
     let format = TextureFormat::Rgba8Unorm;
     let data = vec![0u8, 0u8, 0u8, 0u8];
     let tile_info_map = images.add(Image::new_fill(size, TextureDimension::D2, &data, format));
+    let tile_flood_map = images.add(Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0u8],
+        TextureFormat::R8Unorm,
+    ));
     let tile_base_material = tile_materials.add(TileMaterial {
         settings: InkSettings::default(),
         info_map: tile_info_map.clone(),
+        flood_map: tile_flood_map.clone(),
     });
 
     let material = TileMaterial {
@@ -45,11 +128,16 @@ pub fn setup_board_materials(
             ..Default::default()
         },
         info_map: tile_info_map.clone(),
+        flood_map: tile_flood_map.clone(),
     };
     let decoration_material = tile_materials.add(material);
 
     let selector = meshes.add(shape::Box::new(1., 0.5, 1.).into());
 
+    meshes.set_untracked(TUNNEL_LINE, box_mesh(&[towpath(0., 0.5)]));
+    meshes.set_untracked(TUNNEL_END, box_mesh(&[towpath(0., 0.5), portal()]));
+    meshes.set_untracked(TUNNEL_CENTER, box_mesh(&[towpath(-0.2, 0.2)]));
+
     let selector_base = materials.add(StandardMaterial {
         base_color: Color::rgba(0., 0., 0., 0.0),
         alpha_mode: AlphaMode::Blend,
@@ -76,6 +164,7 @@ pub fn setup_board_materials(
 
     commands.insert_resource(BoardRuntimeAssets {
         tile_info_map,
+        tile_flood_map,
         tile_base_material,
         decoration_material,
         selector,
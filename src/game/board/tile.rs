@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::assets::CanalManiaAssets;
 
+use super::{TUNNEL_CENTER, TUNNEL_END, TUNNEL_LINE};
+
 #[derive(Component, Default, Debug, Clone, FromReflect, Reflect, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct Tile {
@@ -21,6 +23,19 @@ pub struct Tile {
     pub wetness: Wetness,
     #[serde(default)]
     pub cost_modifier: TileCostModifier,
+    /// How many lock operations a water source can keep running - `None` never runs out.
+    #[serde(default)]
+    pub water_capacity: Option<usize>,
+    /// The height of the water surface, when it sits apart from the ground - like a canal on an
+    /// embankment or in a cutting. `None` leaves it to the tile's contents.
+    #[serde(default)]
+    pub water_surface: Option<usize>,
+    /// Whether water has spilled over a canal's banks onto this tile.
+    #[serde(default)]
+    pub flooded: bool,
+    /// How far the ground has been built up (positive) or cut down (negative) since the level began.
+    #[serde(default)]
+    pub earthworks: isize,
 }
 
 #[derive(Clone, Copy, Debug, Reflect, FromReflect, Serialize, Deserialize, Hash, PartialEq, Eq)]
@@ -70,9 +85,7 @@ impl Ord for WetnessSource {
             (WetnessSource::None, WetnessSource::Source(_, _)) => std::cmp::Ordering::Greater,
             (WetnessSource::Source(_, _), WetnessSource::None) => std::cmp::Ordering::Less,
             (WetnessSource::Source(x1, y1), WetnessSource::Source(x2, y2)) => {
-                let a = x1 * 10000 + y1;
-                let b = x2 * 10000 + y2;
-                a.cmp(&b)
+                (x1, y1).cmp(&(x2, y2))
             }
         }
     }
@@ -80,7 +93,7 @@ impl Ord for WetnessSource {
 
 #[cfg(test)]
 mod test {
-    use super::WetnessSource;
+    use super::{Tile, TileContents, WetnessSource, BRIDGE_COST, MAX_HEIGHT, ROAD_CLEARING_COST};
 
     #[test]
     fn wetness_source_order() {
@@ -91,10 +104,60 @@ mod test {
         assert!(a > b);
         assert!(b > c);
     }
+
+    #[test]
+    fn wetness_source_order_on_wide_boards() {
+        let a = WetnessSource::Source(1, 0);
+        let b = WetnessSource::Source(0, 10000);
+
+        assert!(a > b);
+    }
+
+    #[test]
+    fn earthworks_cost_more_the_further_they_go() {
+        let mut tile = Tile {
+            z: 2,
+            ..Default::default()
+        };
+        assert_eq!(tile.get_cutting_cost(), Some(2));
+        tile.earthworks = -2;
+        assert_eq!(tile.get_cutting_cost(), Some(6));
+        assert_eq!(tile.get_embankment_cost(), Some(2));
+
+        tile.z = 0;
+        assert_eq!(tile.get_cutting_cost(), None);
+        tile.z = MAX_HEIGHT;
+        assert_eq!(tile.get_embankment_cost(), None);
+    }
+
+    #[test]
+    fn only_canals_pay_to_bridge_roads() {
+        let field = Tile::default();
+        let road = Tile {
+            contents: TileContents::Road,
+            ..Default::default()
+        };
+        assert_eq!(
+            road.get_canal_cost(),
+            field.get_canal_cost().map(|c| c + BRIDGE_COST)
+        );
+        assert_eq!(
+            road.get_dig_cost(),
+            field.get_dig_cost().map(|c| c + ROAD_CLEARING_COST)
+        );
+        assert_eq!(
+            road.get_lock_cost(),
+            field.get_lock_cost().map(|c| c + ROAD_CLEARING_COST)
+        );
+        assert_eq!(
+            road.get_aquaduct_cost(),
+            field.get_aquaduct_cost().map(|c| c + ROAD_CLEARING_COST)
+        );
+    }
 }
 
 #[derive(Component, Default, Clone, Debug, Reflect)]
-pub struct TileNeighbours(pub [Option<Entity>; 4]);
+pub struct TileNeighbours(pub Vec<Option<Entity>>);
 
 #[derive(Debug, Clone, Copy, Reflect, FromReflect, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TileType {
@@ -118,8 +181,28 @@ pub enum TileContents {
     Lock,
     Aquaduct(usize),
     River,
+    Reservoir,
+    PumpingStation,
+    Tunnel(usize),
+    /// A canal with a road bridge carried over it.
+    Bridge,
 }
 
+/// How many lock operations a reservoir can supply, unless the level says otherwise.
+pub const RESERVOIR_CAPACITY: usize = 3;
+
+/// What it costs on top of digging to carry a road over a new canal.
+pub const BRIDGE_COST: usize = 3;
+
+/// What it costs on top of digging to clear a road away for anything other than a canal.
+pub const ROAD_CLEARING_COST: usize = 1;
+
+/// The highest the ground can be raised to and still be drawn.
+pub const MAX_HEIGHT: usize = 9;
+
+/// What it costs to move one level of earth - each level deeper or higher costs this much more.
+pub const EARTHWORK_COST: usize = 2;
+
 impl Default for TileContents {
     fn default() -> Self {
         Self::None
@@ -135,6 +218,10 @@ impl TileContents {
             TileContents::Lock => Handle::default(),
             TileContents::Aquaduct(_) => assets.aquaduct_center.clone(),
             TileContents::River => assets.river_center.clone(),
+            TileContents::Reservoir => assets.canal_center.clone(),
+            TileContents::PumpingStation => Handle::default(),
+            TileContents::Tunnel(_) => TUNNEL_CENTER.typed(),
+            TileContents::Bridge => assets.canal_center.clone(),
         }
     }
     pub fn line(&self, assets: &CanalManiaAssets) -> Handle<Mesh> {
@@ -145,6 +232,10 @@ impl TileContents {
             TileContents::Lock => assets.lock.clone(),
             TileContents::Aquaduct(_) => assets.aquaduct_line.clone(),
             TileContents::River => assets.river_line.clone(),
+            TileContents::Reservoir => assets.canal_line.clone(),
+            TileContents::PumpingStation => assets.lock.clone(),
+            TileContents::Tunnel(_) => TUNNEL_LINE.typed(),
+            TileContents::Bridge => assets.canal_line.clone(),
         }
     }
     pub fn end(&self, assets: &CanalManiaAssets) -> Handle<Mesh> {
@@ -155,11 +246,46 @@ impl TileContents {
             TileContents::Lock => assets.lock.clone(),
             TileContents::Aquaduct(_) => assets.aquaduct_end.clone(),
             TileContents::River => assets.river_end.clone(),
+            TileContents::Reservoir => assets.canal_end.clone(),
+            TileContents::PumpingStation => assets.lock.clone(),
+            TileContents::Tunnel(_) => TUNNEL_END.typed(),
+            TileContents::Bridge => assets.canal_end.clone(),
         }
     }
 }
 
 impl Tile {
+    /// The height of the water surface on this tile - aquaducts carry their water above the ground,
+    /// and tunnels carry it below, unless the tile has a surface of its own.
+    pub fn water_level(&self) -> usize {
+        if let Some(surface) = self.water_surface {
+            return surface;
+        }
+        match self.contents {
+            TileContents::Aquaduct(h) => self.z + h,
+            TileContents::Tunnel(d) => self.z.saturating_sub(d),
+            _ => self.z,
+        }
+    }
+
+    /// Whether a road runs across this tile.
+    pub fn carries_road(&self) -> bool {
+        matches!(self.contents, TileContents::Road | TileContents::Bridge)
+    }
+
+    /// Whether water flows out of this tile on its own - like rivers, the sea and reservoirs.
+    pub fn is_water_source(&self) -> bool {
+        self.wetness == Wetness::WaterSource || self.contents == TileContents::Reservoir
+    }
+
+    /// How many lock operations this tile can supply - `None` if it never runs out.
+    pub fn supply_capacity(&self) -> Option<usize> {
+        match self.contents {
+            TileContents::Reservoir => Some(self.water_capacity.unwrap_or(RESERVOIR_CAPACITY)),
+            _ => self.water_capacity,
+        }
+    }
+
     fn get_modified_cost(&self, cost: usize) -> Option<usize> {
         match self.cost_modifier {
             TileCostModifier::None => Some(cost),
@@ -168,8 +294,28 @@ impl Tile {
         }
     }
 
+    /// Digging out the tile, and clearing away any road on it - anything but a canal built on it
+    /// costs this and more.
     pub fn get_dig_cost(&self) -> Option<usize> {
-        if self.contents == TileContents::River {
+        self.get_digging_cost(if self.contents == TileContents::Road {
+            ROAD_CLEARING_COST
+        } else {
+            0
+        })
+    }
+
+    /// Digging a canal, and bridging any road it cuts across. The other builders clear the road
+    /// away instead, so only canals pay for a bridge.
+    pub fn get_canal_cost(&self) -> Option<usize> {
+        self.get_digging_cost(if self.contents == TileContents::Road {
+            BRIDGE_COST
+        } else {
+            0
+        })
+    }
+
+    fn get_digging_cost(&self, extra: usize) -> Option<usize> {
+        if self.contents == TileContents::River || self.flooded {
             return None;
         }
         let type_cost = match self.tile_type {
@@ -178,8 +324,7 @@ impl Tile {
             TileType::City => 6,
             TileType::Sea => 1,
         };
-        let road_cost = usize::from(self.contents == TileContents::Road);
-        self.get_modified_cost(type_cost + road_cost)
+        self.get_modified_cost(type_cost + extra)
     }
 
     pub fn get_lock_cost(&self) -> Option<usize> {
@@ -190,6 +335,40 @@ impl Tile {
         self.get_dig_cost().map(|a| a + 2)
     }
 
+    pub fn get_reservoir_cost(&self) -> Option<usize> {
+        self.get_dig_cost().map(|a| a + 4)
+    }
+
+    pub fn get_pumping_station_cost(&self) -> Option<usize> {
+        self.get_dig_cost().map(|a| a + 5)
+    }
+
+    pub fn get_tunnel_cost(&self, depth: usize) -> Option<usize> {
+        self.get_dig_cost().map(|a| a + 2 + 2 * depth)
+    }
+
+    /// Lowering the ground by a level - cheap at first, but each level deeper costs more.
+    pub fn get_cutting_cost(&self) -> Option<usize> {
+        if !self.can_move_earth() || self.z == 0 {
+            return None;
+        }
+        let depth = (1 - self.earthworks).max(1) as usize;
+        self.get_modified_cost(EARTHWORK_COST * depth)
+    }
+
+    /// Raising the ground by a level - cheap at first, but each level higher costs more.
+    pub fn get_embankment_cost(&self) -> Option<usize> {
+        if !self.can_move_earth() || self.z >= MAX_HEIGHT {
+            return None;
+        }
+        let height = (1 + self.earthworks).max(1) as usize;
+        self.get_modified_cost(EARTHWORK_COST * height)
+    }
+
+    fn can_move_earth(&self) -> bool {
+        self.contents == TileContents::None && self.tile_type != TileType::Sea && !self.flooded
+    }
+
     pub fn get_demolish_cost(&self) -> Option<usize> {
         match self.contents {
             TileContents::None => None,
@@ -198,6 +377,20 @@ impl Tile {
             TileContents::Lock => Some(4),
             TileContents::Aquaduct(h) => Some(5 * h),
             TileContents::River => None,
+            TileContents::Reservoir => Some(5),
+            TileContents::PumpingStation => Some(6),
+            TileContents::Tunnel(d) => Some(5 * d),
+            TileContents::Bridge => Some(3),
+        }
+    }
+
+    /// What the owners are paid when this tile floods.
+    pub fn get_flood_compensation(&self) -> usize {
+        match self.tile_type {
+            TileType::Land => 1,
+            TileType::Farm => 4,
+            TileType::City => 8,
+            TileType::Sea => 0,
         }
     }
 
@@ -261,11 +454,3 @@ impl Tile {
             .collect()
     }
 }
-
-pub fn tile_position(x: Option<usize>, y: Option<usize>) -> Option<(usize, usize)> {
-    if let (Some(x), Some(y)) = (x, y) {
-        Some((x, y))
-    } else {
-        None
-    }
-}
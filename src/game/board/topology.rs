@@ -0,0 +1,234 @@
+use bevy::reflect::{FromReflect, Reflect};
+use serde::{Deserialize, Serialize};
+
+/// How the tiles on a board are joined up - which tiles count as next to one another.
+pub trait Topology {
+    /// Where the tiles next to (x, y) lie, relative to it - always in the same order for a
+    /// given row, so callers can tell the directions apart.
+    fn offsets(&self, x: usize, y: usize) -> &'static [(isize, isize)];
+
+    /// The most neighbours any one tile can have.
+    fn max_neighbours(&self) -> usize;
+
+    /// How far along the tiles in row y are pushed, in tiles.
+    fn row_shift(&self, _y: isize) -> f32 {
+        0.
+    }
+
+    /// Where the centre of (x, y) lies on the board, in tiles.
+    fn centre(&self, x: usize, y: usize) -> (f32, f32) {
+        (x as f32 + self.row_shift(y as isize), y as f32)
+    }
+
+    /// How far the centres of the tiles next to (x, y) lie from its own, in the same order as
+    /// `offsets` - whether or not they're on the board.
+    fn centre_offsets(&self, x: usize, y: usize) -> Vec<(f32, f32)> {
+        let shift = self.row_shift(y as isize);
+        self.offsets(x, y)
+            .iter()
+            .map(|(dx, dy)| {
+                let dx = *dx as f32 + self.row_shift(y as isize + dy) - shift;
+                (dx, *dy as f32)
+            })
+            .collect()
+    }
+
+    /// The positions of the tiles next to (x, y), in the same order as `offsets` - `None` where
+    /// that neighbour would be off the edge of the board.
+    fn neighbours(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Vec<Option<(usize, usize)>> {
+        self.offsets(x, y)
+            .iter()
+            .map(|(dx, dy)| {
+                let x = x.checked_add_signed(*dx).filter(|x| *x < width)?;
+                let y = y.checked_add_signed(*dy).filter(|y| *y < height)?;
+                Some((x, y))
+            })
+            .collect()
+    }
+}
+
+/// Tiles joined to the tiles above, left, right and below them - in that order.
+pub struct SquareGrid;
+
+const SQUARE_OFFSETS: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+impl Topology for SquareGrid {
+    fn offsets(&self, _x: usize, _y: usize) -> &'static [(isize, isize)] {
+        &SQUARE_OFFSETS
+    }
+
+    fn max_neighbours(&self) -> usize {
+        SQUARE_OFFSETS.len()
+    }
+}
+
+/// Tiles joined along their diagonals as well as their sides, so canals can cut corners.
+/// The sides come first, in the same order as a `SquareGrid`.
+pub struct DiagonalGrid;
+
+const DIAGONAL_OFFSETS: [(isize, isize); 8] = [
+    (0, -1),
+    (-1, 0),
+    (1, 0),
+    (0, 1),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+    (1, 1),
+];
+
+impl Topology for DiagonalGrid {
+    fn offsets(&self, _x: usize, _y: usize) -> &'static [(isize, isize)] {
+        &DIAGONAL_OFFSETS
+    }
+
+    fn max_neighbours(&self) -> usize {
+        DIAGONAL_OFFSETS.len()
+    }
+}
+
+/// Hexagonal tiles, laid out in rows with every odd row pushed half a tile to the right.
+/// Each tile has two neighbours in its own row, and two in the rows above and below.
+pub struct HexGrid;
+
+const EVEN_ROW_OFFSETS: [(isize, isize); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+const ODD_ROW_OFFSETS: [(isize, isize); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+
+impl Topology for HexGrid {
+    fn offsets(&self, _x: usize, y: usize) -> &'static [(isize, isize)] {
+        if y % 2 == 0 {
+            &EVEN_ROW_OFFSETS
+        } else {
+            &ODD_ROW_OFFSETS
+        }
+    }
+
+    fn max_neighbours(&self) -> usize {
+        EVEN_ROW_OFFSETS.len()
+    }
+
+    fn row_shift(&self, y: isize) -> f32 {
+        if y.rem_euclid(2) == 1 {
+            0.5
+        } else {
+            0.
+        }
+    }
+}
+
+/// The topology a level is laid out on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum GridTopology {
+    Square,
+    Diagonal,
+    Hex,
+}
+
+impl Default for GridTopology {
+    fn default() -> Self {
+        Self::Square
+    }
+}
+
+impl GridTopology {
+    pub fn is_square(&self) -> bool {
+        *self == Self::Square
+    }
+
+    fn grid(&self) -> &'static dyn Topology {
+        match self {
+            GridTopology::Square => &SquareGrid,
+            GridTopology::Diagonal => &DiagonalGrid,
+            GridTopology::Hex => &HexGrid,
+        }
+    }
+}
+
+impl Topology for GridTopology {
+    fn offsets(&self, x: usize, y: usize) -> &'static [(isize, isize)] {
+        self.grid().offsets(x, y)
+    }
+
+    fn max_neighbours(&self) -> usize {
+        self.grid().max_neighbours()
+    }
+
+    fn row_shift(&self, y: isize) -> f32 {
+        self.grid().row_shift(y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GridTopology, Topology};
+
+    #[test]
+    fn neighbours_stop_at_the_edge_of_the_board() {
+        assert_eq!(
+            GridTopology::Square.neighbours(0, 0, 3, 3),
+            vec![None, None, Some((1, 0)), Some((0, 1))]
+        );
+        assert_eq!(
+            GridTopology::Diagonal
+                .neighbours(1, 1, 3, 3)
+                .into_iter()
+                .flatten()
+                .count(),
+            8
+        );
+    }
+
+    #[test]
+    fn hex_rows_are_offset() {
+        let even = GridTopology::Hex.neighbours(1, 2, 4, 4);
+        let odd = GridTopology::Hex.neighbours(1, 1, 4, 4);
+        assert!(even.contains(&Some((0, 1))) && !even.contains(&Some((2, 1))));
+        assert!(odd.contains(&Some((2, 0))) && !odd.contains(&Some((0, 0))));
+
+        // Neighbours always agree with each other
+        for (x, y) in [(1, 1), (1, 2), (2, 2)] {
+            for (nx, ny) in GridTopology::Hex
+                .neighbours(x, y, 4, 4)
+                .into_iter()
+                .flatten()
+            {
+                assert!(GridTopology::Hex
+                    .neighbours(nx, ny, 4, 4)
+                    .contains(&Some((x, y))));
+            }
+        }
+    }
+
+    #[test]
+    fn hex_neighbours_are_the_closest_tiles() {
+        let hex = GridTopology::Hex;
+        assert_eq!(hex.centre(1, 1), (1.5, 1.));
+        assert_eq!(hex.centre(1, 2), (1., 2.));
+
+        for (x, y) in [(2, 2), (2, 3)] {
+            let (cx, cy) = hex.centre(x, y);
+            let neighbours = hex.neighbours(x, y, 5, 5);
+            let offsets = hex.centre_offsets(x, y);
+            for (neighbour, (dx, dy)) in neighbours.iter().zip(&offsets) {
+                let (nx, ny) = neighbour.unwrap();
+                let (ncx, ncy) = hex.centre(nx, ny);
+                assert_eq!((ncx - cx, ncy - cy), (*dx, *dy));
+                assert!(dx.hypot(*dy) < 1.2);
+            }
+            // Every other tile is further away than any of the neighbours
+            for (ox, oy) in (0..5).flat_map(|x| (0..5).map(move |y| (x, y))) {
+                if (ox, oy) == (x, y) || neighbours.contains(&Some((ox, oy))) {
+                    continue;
+                }
+                let (ocx, ocy) = hex.centre(ox, oy);
+                assert!((ocx - cx).hypot(ocy - cy) > 1.2);
+            }
+        }
+    }
+}
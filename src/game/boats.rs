@@ -0,0 +1,374 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use super::{
+    board::*,
+    game_state::GameState,
+    level::{Level, LevelEvent, LevelEventType, PendingLevelEvents},
+    simulation::{passage_cost, PlayTime, WaterNetwork},
+    water_cycle::WaterLevels,
+};
+
+/// How often a new boat sets off, in seconds.
+const BOAT_LAUNCH_INTERVAL: f32 = 4.;
+/// How many boats can be on the water at once.
+const MAX_BOATS: usize = 8;
+/// How many open water tiles a boat crosses each second.
+const BOAT_SPEED: f32 = 1.5;
+/// How far back deliveries count towards throughput, in seconds.
+const THROUGHPUT_WINDOW: f32 = 60.;
+
+pub struct BoatsPlugin;
+
+impl Plugin for BoatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Ports>()
+            .init_resource::<BoatTraffic>()
+            .init_resource::<BoatLaunchTimer>()
+            .add_enter_system(GameState::Setup, reset_launch_timer)
+            .add_system(record_ports.run_if(traffic_running))
+            .add_system(
+                launch_boats
+                    .run_if(traffic_running)
+                    .after("water_simulation"),
+            )
+            .add_system(move_boats.run_if(traffic_running))
+            .add_system(check_deliveries.run_in_state(GameState::InGame));
+    }
+}
+
+/// Whether boats are on the move. They carry on once the level's complete, so a canal that
+/// finishes the level the moment its goals join still gets to carry its traffic.
+pub fn traffic_running(state: Res<CurrentState<GameState>>) -> bool {
+    matches!(state.0, GameState::InGame | GameState::Complete)
+}
+
+/// The tiles boats travel between - every goal the level has set so far.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct Ports(pub Vec<(usize, usize)>);
+
+/// A load a boat has carried from one port to another.
+#[derive(Debug, Clone, Copy)]
+pub struct Delivery {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub journey_time: f32,
+    pub arrived_at: f32,
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct BoatTraffic {
+    pub deliveries: Vec<Delivery>,
+    next_route: usize,
+}
+
+impl BoatTraffic {
+    /// How many loads arrived in the last minute.
+    pub fn boats_per_minute(&self, now: f32) -> f32 {
+        let recent = self
+            .deliveries
+            .iter()
+            .filter(|d| now - d.arrived_at <= THROUGHPUT_WINDOW)
+            .count();
+        recent as f32 * 60. / THROUGHPUT_WINDOW
+    }
+
+    pub fn average_journey_time(&self) -> Option<f32> {
+        if self.deliveries.is_empty() {
+            return None;
+        }
+        let total: f32 = self.deliveries.iter().map(|d| d.journey_time).sum();
+        Some(total / self.deliveries.len() as f32)
+    }
+
+    pub fn delivered(&self, from: Option<(usize, usize)>, to: Option<(usize, usize)>) -> usize {
+        self.deliveries
+            .iter()
+            .filter(|d| from.map(|f| f == d.from).unwrap_or(true))
+            .filter(|d| to.map(|t| t == d.to).unwrap_or(true))
+            .count()
+    }
+}
+
+#[derive(Resource)]
+struct BoatLaunchTimer(Timer);
+
+impl Default for BoatLaunchTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(
+            BOAT_LAUNCH_INTERVAL,
+            TimerMode::Repeating,
+        ))
+    }
+}
+
+/// Each level, and each retry, waits the full interval for its first boat.
+fn reset_launch_timer(mut commands: Commands) {
+    commands.insert_resource(BoatLaunchTimer::default());
+}
+
+#[derive(Component, Debug)]
+pub struct Boat {
+    from: (usize, usize),
+    to: (usize, usize),
+    route: Vec<(usize, usize)>,
+    step: usize,
+    progress: f32,
+    launched_at: f32,
+}
+
+fn record_ports(
+    level: Res<Level>,
+    tiles: Query<&Tile, Changed<Tile>>,
+    mut ports: ResMut<Ports>,
+    mut traffic: ResMut<BoatTraffic>,
+) {
+    if level.is_changed() {
+        *ports = Ports::default();
+        *traffic = BoatTraffic::default();
+    }
+    for tile in tiles.iter() {
+        if tile.is_goal && !ports.0.contains(&(tile.x, tile.y)) {
+            ports.0.push((tile.x, tile.y));
+        }
+    }
+}
+
+fn launch_boats(
+    mut commands: Commands,
+    time: Res<Time>,
+    play_time: Res<PlayTime>,
+    mut timer: ResMut<BoatLaunchTimer>,
+    ports: Res<Ports>,
+    mut traffic: ResMut<BoatTraffic>,
+    tiles: Query<&Tile>,
+    boards: Query<(Entity, &Board)>,
+    boats: Query<&Boat>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut boat_assets: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+    levels: Res<WaterLevels>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() || boats.iter().count() >= MAX_BOATS {
+        return;
+    }
+    let (board_entity, board) = match boards.get_single() {
+        Ok(board) => board,
+        Err(_) => return,
+    };
+
+    let routes = ports
+        .0
+        .iter()
+        .flat_map(|from| ports.0.iter().map(move |to| (*from, *to)))
+        .filter(|(from, to)| from != to)
+        .collect::<Vec<_>>();
+    if routes.is_empty() {
+        return;
+    }
+
+    let network = WaterNetwork::new(board.width, board.height, tiles.iter())
+        .with_topology(board.topology)
+        .with_levels(levels.tide, levels.river);
+    for offset in 0..routes.len() {
+        let index = (traffic.next_route + offset) % routes.len();
+        let (from, to) = routes[index];
+        if let Some(route) = network.route(from, to) {
+            traffic.next_route = index + 1;
+
+            let (mesh, material) = boat_assets
+                .get_or_insert_with(|| {
+                    (
+                        meshes.add(Mesh::from(shape::Box::new(0.5, 0.15, 0.25))),
+                        materials.add(Color::rgb(0.45, 0.3, 0.15).into()),
+                    )
+                })
+                .clone();
+            let start = network
+                .tile(from.0, from.1)
+                .map(|tile| boat_position(board, tile));
+
+            commands.entity(board_entity).with_children(|parent| {
+                parent.spawn((
+                    Boat {
+                        from,
+                        to,
+                        route,
+                        step: 0,
+                        progress: 0.,
+                        launched_at: play_time.0,
+                    },
+                    PbrBundle {
+                        mesh,
+                        material,
+                        transform: Transform::from_translation(start.unwrap_or_default()),
+                        ..Default::default()
+                    },
+                ));
+            });
+            return;
+        }
+    }
+}
+
+fn boat_position(board: &Board, tile: &Tile) -> Vec3 {
+    let (x, y) = board.topology.centre(tile.x, tile.y);
+    Vec3::new(x, (tile.water_level() as f32 + 1.) / 6., y)
+}
+
+fn move_boats(
+    mut commands: Commands,
+    time: Res<Time>,
+    play_time: Res<PlayTime>,
+    mut boats: Query<(Entity, &mut Boat, &mut Transform)>,
+    tiles: Query<&Tile>,
+    boards: Query<&Board>,
+    mut traffic: ResMut<BoatTraffic>,
+) {
+    let board = match boards.get_single() {
+        Ok(board) => board,
+        Err(_) => return,
+    };
+    let tile_at = |position: &(usize, usize)| {
+        board
+            .children
+            .get(position)
+            .and_then(|entity| tiles.get(*entity).ok())
+    };
+
+    for (entity, mut boat, mut transform) in boats.iter_mut() {
+        let (current, next) = match (
+            boat.route.get(boat.step).and_then(tile_at),
+            boat.route.get(boat.step + 1).and_then(tile_at),
+        ) {
+            (Some(current), Some(next)) => (current, next),
+            _ => {
+                traffic.deliveries.push(Delivery {
+                    from: boat.from,
+                    to: boat.to,
+                    journey_time: play_time.0 - boat.launched_at,
+                    arrived_at: play_time.0,
+                });
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        if next.wetness == Wetness::Dry {
+            // The water's gone - the boat is stranded and the load is lost
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        boat.progress += time.delta_seconds() * BOAT_SPEED / passage_cost(next) as f32;
+        if boat.progress >= 1. {
+            boat.progress -= 1.;
+            boat.step += 1;
+        }
+
+        let from = boat_position(board, current);
+        let to = boat_position(board, next);
+        transform.translation = from.lerp(to, boat.progress.min(1.));
+        transform.look_at(to, Vec3::Y);
+    }
+}
+
+fn check_deliveries(
+    traffic: Res<BoatTraffic>,
+    mut level_events: ResMut<PendingLevelEvents>,
+    mut events: EventWriter<LevelEvent>,
+) {
+    if !traffic.is_changed() {
+        return;
+    }
+    let fired = level_events.fire(|trigger| match trigger {
+        LevelEventType::LoadsDelivered(loads, from, to) => {
+            Some(traffic.delivered(*from, *to) >= *loads)
+        }
+        _ => None,
+    });
+    for event in fired {
+        info!("Loads Delivered Event {event:?}");
+        events.send(event);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{move_boats, Boat, BoatTraffic, Delivery};
+    use crate::game::{board::*, simulation::PlayTime};
+    use bevy::prelude::*;
+
+    fn delivery(from: (usize, usize), to: (usize, usize), arrived_at: f32) -> Delivery {
+        Delivery {
+            from,
+            to,
+            journey_time: 10.,
+            arrived_at,
+        }
+    }
+
+    #[test]
+    fn throughput_only_counts_the_last_minute() {
+        let traffic = BoatTraffic {
+            deliveries: vec![
+                delivery((0, 0), (5, 0), 10.),
+                delivery((5, 0), (0, 0), 50.),
+                delivery((0, 0), (5, 0), 80.),
+            ],
+            next_route: 0,
+        };
+
+        assert_eq!(traffic.boats_per_minute(70.), 3.);
+        assert_eq!(traffic.boats_per_minute(100.), 2.);
+        assert_eq!(traffic.boats_per_minute(200.), 0.);
+        assert_eq!(traffic.average_journey_time(), Some(10.));
+        assert_eq!(traffic.delivered(Some((0, 0)), None), 2);
+        assert_eq!(traffic.delivered(None, Some((0, 0))), 1);
+        assert_eq!(traffic.delivered(None, None), 3);
+    }
+
+    #[test]
+    fn journeys_are_timed_in_play_time() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<BoatTraffic>();
+        world.insert_resource(PlayTime(25.));
+
+        let mut board = Board {
+            width: 2,
+            height: 1,
+            ..Default::default()
+        };
+        for x in 0..2 {
+            let tile = Tile {
+                x,
+                contents: TileContents::Canal,
+                wetness: Wetness::Wet(1),
+                ..Default::default()
+            };
+            board.children.insert((x, 0), world.spawn(tile).id());
+        }
+        world.spawn(board);
+        world.spawn((
+            Boat {
+                from: (0, 0),
+                to: (1, 0),
+                route: vec![(1, 0)],
+                step: 0,
+                progress: 0.,
+                launched_at: 5.,
+            },
+            Transform::default(),
+        ));
+
+        let mut stage = SystemStage::single_threaded().with_system(move_boats);
+        stage.run(&mut world);
+
+        let traffic = world.resource::<BoatTraffic>();
+        assert_eq!(traffic.deliveries.len(), 1);
+        assert_eq!(traffic.deliveries[0].journey_time, 20.);
+        assert_eq!(traffic.deliveries[0].arrived_at, 25.);
+        assert_eq!(world.query::<&Boat>().iter(&world).count(), 0);
+    }
+}
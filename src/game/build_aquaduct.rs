@@ -72,6 +72,7 @@ fn build_aquaduct(
                             resources.cost_so_far += cost;
                             tile.wetness = Wetness::Dry;
                             tile.contents = TileContents::Aquaduct(*height);
+                            tile.water_surface = None;
                         }
                     }
                 }
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::{ConditionHelpers, IntoConditionalSystem};
+
+use super::{
+    board::*,
+    game_state::{GameActionMode, GameActions, GameResources, GameState},
+};
+
+pub struct BuildEmbankmentPlugin;
+
+impl Plugin for BuildEmbankmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            trigger_build_embankment
+                .run_in_state(GameActionMode::BuildEmbankment)
+                .run_not_in_state(GameState::Description),
+        )
+        .add_system(
+            build_embankment
+                .run_in_state(GameActionMode::BuildEmbankment)
+                .run_not_in_state(GameState::Description),
+        );
+    }
+}
+
+fn trigger_build_embankment(
+    mut event_writer: EventWriter<GameActions>,
+    mut event_reader: EventReader<TileEvent>,
+    buttons: Res<Input<MouseButton>>,
+) {
+    for event in event_reader.iter() {
+        match event {
+            TileEvent::Clicked(tile, _) => {
+                event_writer.send(GameActions::BuildEmbankment(tile.clone()));
+            }
+            TileEvent::HoverStarted(tile, _) => {
+                if buttons.pressed(MouseButton::Left) {
+                    event_writer.send(GameActions::BuildEmbankment(tile.clone()));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn build_embankment(
+    mut event_reader: EventReader<GameActions>,
+    mut tiles: Query<&mut Tile>,
+    board: Query<&Board>,
+    mut resources: ResMut<GameResources>,
+) {
+    if let Ok(board) = board.get_single() {
+        for event in event_reader.iter() {
+            if let GameActions::BuildEmbankment(tile) = event {
+                let my_position = (tile.x, tile.y);
+                if let Some(entity) = board.children.get(&my_position) {
+                    if let Ok(mut tile) = tiles.get_mut(*entity) {
+                        if let Some(cost) = tile.get_embankment_cost() {
+                            resources.cost_so_far += cost;
+                            tile.z += 1;
+                            tile.earthworks += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
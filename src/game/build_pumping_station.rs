@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::{AppLooplessStateExt, ConditionHelpers, IntoConditionalSystem};
+
+use super::{
+    board::*,
+    game_state::{GameActionMode, GameActions, GameResources, GameState},
+};
+
+/// How often a working pumping station adds its running cost.
+const PUMP_RUNNING_INTERVAL: f32 = 5.;
+/// What a working pumping station costs each interval.
+const PUMP_RUNNING_COST: usize = 1;
+
+pub struct BuildPumpingStationPlugin;
+
+impl Plugin for BuildPumpingStationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PumpRunningTimer>()
+            .add_enter_system(GameState::Setup, reset_pump_timer)
+            .add_system(
+                trigger_build_pumping_station
+                    .run_in_state(GameActionMode::BuildPumpingStation)
+                    .run_not_in_state(GameState::Description),
+            )
+            .add_system(
+                build_pumping_station
+                    .run_in_state(GameActionMode::BuildPumpingStation)
+                    .run_not_in_state(GameState::Description),
+            )
+            .add_system(run_pumping_stations.run_in_state(GameState::InGame));
+    }
+}
+
+#[derive(Resource)]
+struct PumpRunningTimer(Timer);
+
+impl Default for PumpRunningTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(
+            PUMP_RUNNING_INTERVAL,
+            TimerMode::Repeating,
+        ))
+    }
+}
+
+/// Each level, and each retry, starts a fresh interval rather than carrying on the last one's.
+fn reset_pump_timer(mut commands: Commands) {
+    commands.insert_resource(PumpRunningTimer::default());
+}
+
+fn trigger_build_pumping_station(
+    mut event_writer: EventWriter<GameActions>,
+    mut event_reader: EventReader<TileEvent>,
+    buttons: Res<Input<MouseButton>>,
+) {
+    for event in event_reader.iter() {
+        match event {
+            TileEvent::Clicked(tile, _) => {
+                event_writer.send(GameActions::BuildPumpingStation(tile.clone()));
+            }
+            TileEvent::HoverStarted(tile, _) => {
+                if buttons.pressed(MouseButton::Left) {
+                    event_writer.send(GameActions::BuildPumpingStation(tile.clone()));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn build_pumping_station(
+    mut event_reader: EventReader<GameActions>,
+    mut tiles: Query<&mut Tile>,
+    board: Query<&Board>,
+    mut resources: ResMut<GameResources>,
+) {
+    if let Ok(board) = board.get_single() {
+        for event in event_reader.iter() {
+            if let GameActions::BuildPumpingStation(tile) = event {
+                if !matches!(
+                    tile.contents,
+                    TileContents::PumpingStation | TileContents::River
+                ) {
+                    let my_position = (tile.x, tile.y);
+                    if let Some(entity) = board.children.get(&my_position) {
+                        if let Ok(mut tile) = tiles.get_mut(*entity) {
+                            if let Some(cost) = tile.get_pumping_station_cost() {
+                                resources.cost_so_far += cost;
+                                tile.contents = TileContents::PumpingStation;
+                                tile.wetness = Wetness::Dry;
+                                tile.water_surface = None;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn run_pumping_stations(
+    time: Res<Time>,
+    mut timer: ResMut<PumpRunningTimer>,
+    tiles: Query<&Tile>,
+    mut resources: ResMut<GameResources>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let working = tiles
+        .iter()
+        .filter(|tile| {
+            tile.contents == TileContents::PumpingStation && matches!(tile.wetness, Wetness::Wet(_))
+        })
+        .count();
+    resources.cost_so_far += working * PUMP_RUNNING_COST;
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::{ConditionHelpers, IntoConditionalSystem};
+
+use super::{
+    board::*,
+    game_state::{GameActionMode, GameActions, GameResources, GameState},
+};
+pub struct BuildReservoirPlugin;
+
+impl Plugin for BuildReservoirPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            trigger_build_reservoir
+                .run_in_state(GameActionMode::BuildReservoir)
+                .run_not_in_state(GameState::Description),
+        )
+        .add_system(
+            build_reservoir
+                .run_in_state(GameActionMode::BuildReservoir)
+                .run_not_in_state(GameState::Description),
+        );
+    }
+}
+
+fn trigger_build_reservoir(
+    mut event_writer: EventWriter<GameActions>,
+    mut event_reader: EventReader<TileEvent>,
+    buttons: Res<Input<MouseButton>>,
+) {
+    for event in event_reader.iter() {
+        match event {
+            TileEvent::Clicked(tile, _) => {
+                event_writer.send(GameActions::BuildReservoir(tile.clone()));
+            }
+            TileEvent::HoverStarted(tile, _) => {
+                if buttons.pressed(MouseButton::Left) {
+                    event_writer.send(GameActions::BuildReservoir(tile.clone()));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn build_reservoir(
+    mut event_reader: EventReader<GameActions>,
+    mut tiles: Query<&mut Tile>,
+    board: Query<&Board>,
+    mut resources: ResMut<GameResources>,
+) {
+    if let Ok(board) = board.get_single() {
+        for event in event_reader.iter() {
+            if let GameActions::BuildReservoir(tile) = event {
+                if !matches!(tile.contents, TileContents::Reservoir | TileContents::River) {
+                    let my_position = (tile.x, tile.y);
+                    if let Some(entity) = board.children.get(&my_position) {
+                        if let Ok(mut tile) = tiles.get_mut(*entity) {
+                            if let Some(cost) = tile.get_reservoir_cost() {
+                                resources.cost_so_far += cost;
+                                tile.contents = TileContents::Reservoir;
+                                tile.wetness = Wetness::Dry;
+                                tile.water_surface = None;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
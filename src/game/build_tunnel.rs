@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::{AppLooplessStateExt, ConditionHelpers, IntoConditionalSystem};
+
+use super::{
+    board::*,
+    game_state::{GameActionMode, GameActions, GameResources, GameState},
+};
+pub struct BuildTunnelPlugin;
+
+impl Plugin for BuildTunnelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LastTunnelLevel>()
+            .add_enter_system(GameState::Setup, reset_tunnel_level)
+            .add_system(
+                trigger_build_tunnel
+                    .run_in_state(GameActionMode::BuildTunnel)
+                    .run_not_in_state(GameState::Description),
+            )
+            .add_system(
+                build_tunnel
+                    .run_in_state(GameActionMode::BuildTunnel)
+                    .run_not_in_state(GameState::Description),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct LastTunnelLevel(usize);
+
+/// Tunnels in a new level, or a retry, don't carry on at the depth the last one was dug to.
+fn reset_tunnel_level(mut commands: Commands) {
+    commands.insert_resource(LastTunnelLevel::default());
+}
+
+fn trigger_build_tunnel(
+    mut event_writer: EventWriter<GameActions>,
+    mut event_reader: EventReader<TileEvent>,
+    buttons: Res<Input<MouseButton>>,
+    mut tunnel_level: ResMut<LastTunnelLevel>,
+) {
+    for event in event_reader.iter() {
+        match event {
+            TileEvent::Clicked(tile, _) => {
+                let depth = match tile.contents {
+                    TileContents::Tunnel(d) => d + 1,
+                    _ => 1,
+                };
+                if depth <= tile.z {
+                    event_writer.send(GameActions::BuildTunnel(tile.clone(), depth));
+                    tunnel_level.0 = tile.z - depth;
+                }
+            }
+            TileEvent::HoverStarted(tile, _) => {
+                if buttons.pressed(MouseButton::Left) && tile.z > tunnel_level.0 {
+                    let depth = tile.z - tunnel_level.0;
+                    // Dragging back over the tunnel shouldn't pay for it again
+                    if tile.contents != TileContents::Tunnel(depth) {
+                        event_writer.send(GameActions::BuildTunnel(tile.clone(), depth));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn build_tunnel(
+    mut event_reader: EventReader<GameActions>,
+    mut tiles: Query<&mut Tile>,
+    board: Query<&Board>,
+    mut resources: ResMut<GameResources>,
+) {
+    if let Ok(board) = board.get_single() {
+        for event in event_reader.iter() {
+            if let GameActions::BuildTunnel(tile, depth) = event {
+                let my_position = (tile.x, tile.y);
+                if let Some(entity) = board.children.get(&my_position) {
+                    if let Ok(mut tile) = tiles.get_mut(*entity) {
+                        if let Some(cost) = tile.get_tunnel_cost(*depth) {
+                            resources.cost_so_far += cost;
+                            tile.wetness = Wetness::Dry;
+                            tile.contents = TileContents::Tunnel(*depth);
+                            tile.water_surface = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    board::{GridTopology, TileContents, TileCostModifier, TileType},
+    level::{Level, LevelEvent, LevelTools, NamedPlace, NamedRoad, TileInfo},
+    level_loader::{validate, LevelError, LevelFile, LevelProblem, LEVEL_VERSION},
+    water_cycle::WaterCycle,
+};
+
+/// A level written out as a handful of character grids instead of a list of tiles - one row of
+/// text per row of the board, so the map can be read (and diffed) at a glance.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CompactLevel {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub initial_description: Option<String>,
+    #[serde(default)]
+    pub sidebar_text: Option<String>,
+    pub width: usize,
+    pub height: usize,
+    /// Ground heights, from `0`-`9` then `a`-`z`.
+    pub heights: Vec<String>,
+    /// `L`and, `F`arm, `C`ity or `S`ea.
+    pub types: Vec<String>,
+    /// What's built on each tile - see `CONTENTS`.
+    pub contents: Vec<String>,
+    /// `.` for normal cost, `*` where it costs more and `#` where building is blocked.
+    pub costs: Vec<String>,
+    /// How high each aquaduct is, and how deep each tunnel goes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<((usize, usize), usize)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub goals: Vec<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub water_capacity: Vec<((usize, usize), usize)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub water_surface: Vec<((usize, usize), usize)>,
+    #[serde(default)]
+    pub events: Vec<LevelEvent>,
+    #[serde(default = "LevelTools::unlisted")]
+    pub tools: LevelTools,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roads: Vec<NamedRoad>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub places: Vec<NamedPlace>,
+    #[serde(default, skip_serializing_if = "WaterCycle::is_static")]
+    pub water_cycle: WaterCycle,
+    #[serde(default, skip_serializing_if = "GridTopology::is_square")]
+    pub topology: GridTopology,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub par: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<usize>,
+}
+
+const TYPES: [(char, TileType); 4] = [
+    ('L', TileType::Land),
+    ('F', TileType::Farm),
+    ('C', TileType::City),
+    ('S', TileType::Sea),
+];
+
+/// Aquaducts and tunnels are listed with a span of 0 - the real one is kept in `spans`.
+const CONTENTS: [(char, TileContents); 10] = [
+    ('.', TileContents::None),
+    ('=', TileContents::Road),
+    ('~', TileContents::Canal),
+    ('H', TileContents::Lock),
+    ('A', TileContents::Aquaduct(0)),
+    ('r', TileContents::River),
+    ('R', TileContents::Reservoir),
+    ('P', TileContents::PumpingStation),
+    ('T', TileContents::Tunnel(0)),
+    ('B', TileContents::Bridge),
+];
+
+const COSTS: [(char, TileCostModifier); 3] = [
+    ('.', TileCostModifier::None),
+    ('*', TileCostModifier::Multiplier),
+    ('#', TileCostModifier::Blocked),
+];
+
+fn symbol<T: PartialEq + Copy>(table: &[(char, T)], value: T) -> Option<char> {
+    table.iter().find(|(_, v)| *v == value).map(|(c, _)| *c)
+}
+
+fn lookup<T: Copy>(table: &[(char, T)], symbol: char) -> Option<T> {
+    table.iter().find(|(c, _)| *c == symbol).map(|(_, v)| *v)
+}
+
+/// Splits the span off contents that carry one, so they can share a symbol.
+fn span(contents: TileContents) -> (TileContents, Option<usize>) {
+    match contents {
+        TileContents::Aquaduct(height) => (TileContents::Aquaduct(0), Some(height)),
+        TileContents::Tunnel(depth) => (TileContents::Tunnel(0), Some(depth)),
+        contents => (contents, None),
+    }
+}
+
+fn with_span(contents: TileContents, span: usize) -> TileContents {
+    match contents {
+        TileContents::Aquaduct(_) => TileContents::Aquaduct(span),
+        TileContents::Tunnel(_) => TileContents::Tunnel(span),
+        contents => contents,
+    }
+}
+
+fn tile_at(tiles: &mut [Vec<TileInfo>], (x, y): (usize, usize)) -> Option<&mut TileInfo> {
+    tiles.get_mut(x).and_then(|column| column.get_mut(y))
+}
+
+impl TryFrom<&Level> for CompactLevel {
+    type Error = ron::Error;
+
+    /// Fails on any tile a layer has no symbol for - like ground higher than 35 - rather than
+    /// writing out a level that won't load again.
+    fn try_from(level: &Level) -> Result<Self, Self::Error> {
+        let layer = |name: &str,
+                     f: &dyn Fn(&TileInfo) -> Option<char>|
+         -> Result<Vec<String>, ron::Error> {
+            (0..level.height)
+                .map(|y| {
+                    (0..level.width)
+                        .map(|x| {
+                            level
+                                .tiles
+                                .get(x)
+                                .and_then(|c| c.get(y))
+                                .and_then(f)
+                                .ok_or_else(|| {
+                                    ron::Error::Message(format!(
+                                        "tile ({x}, {y}) can't be written into the {name} layer"
+                                    ))
+                                })
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        let mut spans = vec![];
+        let mut goals = vec![];
+        let mut water_capacity = vec![];
+        let mut water_surface = vec![];
+        for y in 0..level.height {
+            for (x, column) in level.tiles.iter().enumerate() {
+                if let Some(tile) = column.get(y) {
+                    if let (_, Some(span)) = span(tile.contents) {
+                        spans.push(((x, y), span));
+                    }
+                    if tile.is_goal {
+                        goals.push((x, y));
+                    }
+                    if let Some(capacity) = tile.water_capacity {
+                        water_capacity.push(((x, y), capacity));
+                    }
+                    if let Some(surface) = tile.water_surface {
+                        water_surface.push(((x, y), surface));
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            version: level.version,
+            title: level.title.clone(),
+            initial_description: level.initial_description.clone(),
+            sidebar_text: level.sidebar_text.clone(),
+            width: level.width,
+            height: level.height,
+            heights: layer("heights", &|tile| {
+                char::from_digit(u32::try_from(tile.height).ok()?, 36)
+            })?,
+            types: layer("types", &|tile| symbol(&TYPES, tile.tile_type))?,
+            contents: layer("contents", &|tile| symbol(&CONTENTS, span(tile.contents).0))?,
+            costs: layer("costs", &|tile| symbol(&COSTS, tile.cost_modifier))?,
+            spans,
+            goals,
+            water_capacity,
+            water_surface,
+            events: level.events.clone(),
+            tools: level.tools.clone(),
+            roads: level.roads.clone(),
+            places: level.places.clone(),
+            water_cycle: level.water_cycle.clone(),
+            topology: level.topology,
+            par: level.par,
+            budget: level.budget,
+        })
+    }
+}
+
+impl CompactLevel {
+    /// Unpacks the layers back into tiles, pointing at every symbol that couldn't be read.
+    pub fn into_level(self, file: &str) -> Result<Level, Vec<LevelError>> {
+        let mut problems = vec![];
+        let mut report = |position, problem| {
+            problems.push(LevelError {
+                file: file.to_string(),
+                event: None,
+                position,
+                problem,
+            })
+        };
+
+        let mut tiles = vec![vec![TileInfo::default(); self.height]; self.width];
+        type Read = fn(&mut TileInfo, char) -> Option<()>;
+        let layers: [(&str, &Vec<String>, Read); 4] = [
+            ("heights", &self.heights, |tile, c| {
+                tile.height = c.to_digit(36)? as usize;
+                Some(())
+            }),
+            ("types", &self.types, |tile, c| {
+                tile.tile_type = lookup(&TYPES, c)?;
+                Some(())
+            }),
+            ("contents", &self.contents, |tile, c| {
+                tile.contents = lookup(&CONTENTS, c)?;
+                Some(())
+            }),
+            ("costs", &self.costs, |tile, c| {
+                tile.cost_modifier = lookup(&COSTS, c)?;
+                Some(())
+            }),
+        ];
+        for (name, rows, read) in layers {
+            if rows.len() != self.height {
+                report(
+                    None,
+                    LevelProblem::WrongHeight {
+                        height: self.height,
+                        tiles: rows.len(),
+                    },
+                );
+            }
+            for (y, row) in rows.iter().enumerate().take(self.height) {
+                if row.chars().count() != self.width {
+                    report(
+                        Some((0, y)),
+                        LevelProblem::WrongWidth {
+                            width: self.width,
+                            columns: row.chars().count(),
+                        },
+                    );
+                }
+                for (x, c) in row.chars().enumerate().take(self.width) {
+                    if read(&mut tiles[x][y], c).is_none() {
+                        report(Some((x, y)), LevelProblem::UnknownSymbol(name.into(), c));
+                    }
+                }
+            }
+        }
+
+        let positions = self
+            .spans
+            .iter()
+            .chain(self.water_capacity.iter())
+            .chain(self.water_surface.iter())
+            .map(|(position, _)| position)
+            .chain(self.goals.iter());
+        for &(x, y) in positions {
+            if x >= self.width || y >= self.height {
+                report(Some((x, y)), LevelProblem::OffTheBoard);
+            }
+        }
+        for (position, span) in self.spans {
+            if let Some(tile) = tile_at(&mut tiles, position) {
+                tile.contents = with_span(tile.contents, span);
+            }
+        }
+        for position in self.goals {
+            if let Some(tile) = tile_at(&mut tiles, position) {
+                tile.is_goal = true;
+            }
+        }
+        for (position, capacity) in self.water_capacity {
+            if let Some(tile) = tile_at(&mut tiles, position) {
+                tile.water_capacity = Some(capacity);
+            }
+        }
+        for (position, surface) in self.water_surface {
+            if let Some(tile) = tile_at(&mut tiles, position) {
+                tile.water_surface = Some(surface);
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        Ok(Level {
+            version: self.version,
+            tiles,
+            title: self.title,
+            initial_description: self.initial_description,
+            sidebar_text: self.sidebar_text,
+            width: self.width,
+            height: self.height,
+            events: self.events,
+            tools: self.tools,
+            roads: self.roads,
+            places: self.places,
+            water_cycle: self.water_cycle,
+            topology: self.topology,
+            par: self.par,
+            budget: self.budget,
+        })
+    }
+}
+
+/// Writes a level out in the compact format.
+pub fn to_compact_string(level: &Level) -> Result<String, ron::Error> {
+    ron::ser::to_string_pretty(
+        &CompactLevel::try_from(level)?,
+        ron::ser::PrettyConfig::default(),
+    )
+}
+
+/// Reads a compact level and checks it over. The compact format started at version 1, so there's
+/// nothing older to migrate.
+pub fn load_compact_level(file: &str, bytes: &[u8]) -> Result<Level, Vec<LevelError>> {
+    let error = |problem| {
+        vec![LevelError {
+            file: file.to_string(),
+            event: None,
+            position: None,
+            problem,
+        }]
+    };
+    let compact = ron::de::from_bytes::<CompactLevel>(bytes)
+        .map_err(|e| error(LevelProblem::Unreadable(e.to_string())))?;
+    if compact.version > LEVEL_VERSION {
+        return Err(error(LevelProblem::TooNew(compact.version)));
+    }
+    let mut level = compact.into_level(file)?;
+    level.version = LEVEL_VERSION;
+
+    let errors = validate(file, &level);
+    if errors.is_empty() {
+        Ok(level)
+    } else {
+        Err(errors)
+    }
+}
+
+#[derive(Default)]
+pub struct CompactLevelLoader;
+
+impl AssetLoader for CompactLevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let file = load_context.path().display().to_string();
+            load_context.set_default_asset(LoadedAsset::new(LevelFile(load_compact_level(
+                &file, bytes,
+            ))));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lvl.ron"]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load_compact_level, to_compact_string, CompactLevel};
+    use crate::game::level_loader::{load_level, LevelProblem};
+
+    #[test]
+    fn shipped_levels_round_trip() {
+        let levels = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/levels"))
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.to_string_lossy().ends_with(".lvl.json"));
+        for path in levels {
+            let file = path.to_string_lossy();
+            let level = load_level(&file, &std::fs::read(&path).unwrap()).unwrap();
+            let compact = to_compact_string(&level).unwrap();
+            let reloaded = load_compact_level(&file, compact.as_bytes()).unwrap();
+            assert_eq!(
+                serde_json::to_value(&level).unwrap(),
+                serde_json::to_value(&reloaded).unwrap(),
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_symbols_point_at_their_tile() {
+        let level = load_level(
+            "test",
+            br#"{"tiles": [[{"height": 0}], [{"height": 1}]], "title": null,
+                "initial_description": null, "sidebar_text": null, "width": 2, "height": 1}"#,
+        )
+        .unwrap();
+        let mut compact = CompactLevel::try_from(&level).unwrap();
+        assert_eq!(compact.heights, vec!["01".to_string()]);
+
+        compact.types[0] = "L?".into();
+        let errors = compact.into_level("test").unwrap_err();
+        assert_eq!(errors[0].position, Some((1, 0)));
+        assert_eq!(
+            errors[0].problem,
+            LevelProblem::UnknownSymbol("types".into(), '?')
+        );
+    }
+
+    #[test]
+    fn heights_without_a_symbol_are_not_written() {
+        let mut level = load_level(
+            "test",
+            br#"{"tiles": [[{"height": 0}], [{"height": 35}]], "title": null,
+                "initial_description": null, "sidebar_text": null, "width": 2, "height": 1}"#,
+        )
+        .unwrap();
+        assert!(to_compact_string(&level).is_ok());
+
+        level.tiles[1][0].height = 36;
+        assert!(to_compact_string(&level).is_err());
+    }
+
+    #[test]
+    fn levels_without_tools_get_the_same_ones_in_either_format() {
+        let json = load_level(
+            "test",
+            br#"{"tiles": [[{"height": 0}]], "title": null,
+                "initial_description": null, "sidebar_text": null, "width": 1, "height": 1}"#,
+        )
+        .unwrap();
+        let compact = load_compact_level(
+            "test",
+            br#"(width: 1, height: 1, heights: ["0"], types: ["L"], contents: ["."], costs: ["."])"#,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&json.tools).unwrap(),
+            serde_json::to_value(&compact.tools).unwrap()
+        );
+        assert!(!compact.tools.reservoir);
+    }
+
+    #[test]
+    fn partial_tool_lists_leave_the_rest_off() {
+        let level = load_compact_level(
+            "test",
+            br#"(width: 1, height: 1, heights: ["0"], types: ["L"], contents: ["."], costs: ["."],
+                tools: (canal: true, lock: true, aquaduct: false, demolish: true))"#,
+        )
+        .unwrap();
+        assert!(level.tools.lock);
+        assert!(!level.tools.aquaduct);
+        assert!(!level.tools.tunnel);
+    }
+}
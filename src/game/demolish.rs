@@ -56,8 +56,14 @@ fn demolish(
                     if let Ok(mut tile) = tiles.get_mut(*entity) {
                         if let Some(cost) = tile.get_demolish_cost() {
                             resources.cost_so_far += cost;
-                            tile.contents = TileContents::None;
+                            // Filling in a canal under a bridge leaves the road behind
+                            tile.contents = if tile.contents == TileContents::Bridge {
+                                TileContents::Road
+                            } else {
+                                TileContents::None
+                            };
                             tile.wetness = Wetness::Dry;
+                            tile.water_surface = None;
                         }
                     }
                 }
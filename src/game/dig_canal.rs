@@ -53,15 +53,23 @@ fn dig_canal(
             if let GameActions::DigCanal(tile) = event {
                 if !matches!(
                     tile.contents,
-                    TileContents::Canal | TileContents::Lock | TileContents::River
+                    TileContents::Canal
+                        | TileContents::Bridge
+                        | TileContents::Lock
+                        | TileContents::River
                 ) {
                     let my_position = (tile.x, tile.y);
                     if let Some(entity) = board.children.get(&my_position) {
                         if let Ok(mut tile) = tiles.get_mut(*entity) {
-                            if let Some(cost) = tile.get_dig_cost() {
+                            if let Some(cost) = tile.get_canal_cost() {
                                 resources.cost_so_far += cost;
-                                tile.contents = TileContents::Canal;
+                                tile.contents = if tile.contents == TileContents::Road {
+                                    TileContents::Bridge
+                                } else {
+                                    TileContents::Canal
+                                };
                                 tile.wetness = Wetness::Dry;
+                                tile.water_surface = None;
                             }
                         }
                     }
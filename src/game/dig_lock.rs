@@ -59,6 +59,7 @@ fn dig_lock(
                                 resources.cost_so_far += cost;
                                 tile.contents = TileContents::Lock;
                                 tile.wetness = Wetness::Dry;
+                                tile.water_surface = None;
                             }
                         }
                     }
@@ -6,9 +6,14 @@ use iyes_loopless::prelude::*;
 use crate::ui::*;
 
 use super::{
-    board::{Tile, TileContents, TileCostModifier, TileEvent, TileType, Wetness},
+    board::{GridTopology, Tile, TileContents, TileCostModifier, TileEvent, TileType, Wetness},
+    compact_level::to_compact_string,
     game_state::{GameActionMode, GameState},
     level::{Level, TileInfo},
+    level_import::{read_elevations, ImportSettings},
+    level_loader::LEVEL_VERSION,
+    simulation::WaterNetwork,
+    solver::plan_routes,
 };
 
 pub struct EditorUiPlugin;
@@ -35,6 +40,7 @@ enum EditorOperation {
     ToggleWetness,
     SetGoal,
     SetCostModifier(TileCostModifier),
+    SetWaterCapacity(Option<usize>),
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -42,6 +48,7 @@ enum EditorUiElement {
     CurrentModeText,
     Width,
     Height,
+    Topology,
 }
 
 fn display_ui(mut commands: Commands, level: Res<Level>) {
@@ -64,6 +71,7 @@ fn display_ui(mut commands: Commands, level: Res<Level>) {
                     parent.button("goal", "Goals");
                     parent.button("construct", "Construction");
                     parent.button("modifier", "Cost");
+                    parent.button("capacity", "Supply");
                 });
             });
         });
@@ -91,8 +99,18 @@ fn display_ui(mut commands: Commands, level: Res<Level>) {
                         .size(15.);
                     parent.button("height_add", "+").style(ButtonStyle::Small);
                     parent.button("height_sub", "-").style(ButtonStyle::Small);
+                    parent
+                        .text(format!("{:?}", level.topology))
+                        .id(EditorUiElement::Topology)
+                        .size(15.);
+                    parent.button("topology", "Grid").style(ButtonStyle::Small);
                     parent.button("new", "New").style(ButtonStyle::Small);
                     parent.button("save", "Save").style(ButtonStyle::Small);
+                    parent.button("import", "Import").style(ButtonStyle::Small);
+                    parent.button("graph", "Graph").style(ButtonStyle::Small);
+                    parent
+                        .button("plan", "Plan Routes")
+                        .style(ButtonStyle::Small);
                     parent.button("exit_editor", "X").style(ButtonStyle::Small);
                 });
         });
@@ -116,6 +134,10 @@ fn update_labels(
                         EditorOperation::ToggleConstruction(t) => format!("Build {t:?} on tiles"),
                         EditorOperation::ToggleWetness => "Adjust Water Status".to_string(),
                         EditorOperation::SetCostModifier(t) => format!("Set cost to {t:?}"),
+                        EditorOperation::SetWaterCapacity(c) => match c {
+                            Some(c) => format!("Set water supply to {c}"),
+                            None => "Set water supply to unlimited".to_string(),
+                        },
                     }
                 }
                 EditorUiElement::Width => {
@@ -124,6 +146,9 @@ fn update_labels(
                 EditorUiElement::Height => {
                     label.text = level.height.to_string();
                 }
+                EditorUiElement::Topology => {
+                    label.text = format!("{:?}", level.topology);
+                }
             }
         }
     }
@@ -169,6 +194,13 @@ fn button_pressed(
                 _ => TileCostModifier::Blocked,
             };
             commands.insert_resource(NextState(EditorOperation::SetCostModifier(next)));
+        } else if event.0 == "capacity" {
+            let next = match operation.0 {
+                EditorOperation::SetWaterCapacity(Some(c)) if c < 5 => Some(c + 1),
+                EditorOperation::SetWaterCapacity(Some(_)) => None,
+                _ => Some(1),
+            };
+            commands.insert_resource(NextState(EditorOperation::SetWaterCapacity(next)));
         } else if event.0 == "construct" {
             let next = match operation.0 {
                 EditorOperation::ToggleConstruction(t) => match t {
@@ -177,13 +209,31 @@ fn button_pressed(
                     TileContents::River => TileContents::Canal,
                     TileContents::Canal => TileContents::Lock,
                     TileContents::Lock => TileContents::Aquaduct(1),
-                    TileContents::Aquaduct(_) => TileContents::None,
+                    TileContents::Aquaduct(_) => TileContents::Reservoir,
+                    TileContents::Reservoir => TileContents::PumpingStation,
+                    TileContents::PumpingStation => TileContents::Tunnel(1),
+                    TileContents::Tunnel(_) => TileContents::Bridge,
+                    TileContents::Bridge => TileContents::None,
                 },
                 _ => TileContents::Road,
             };
             commands.insert_resource(NextState(EditorOperation::ToggleConstruction(next)));
         } else if event.0 == "save" {
             save(&tiles, &level);
+        } else if event.0 == "import" {
+            import(&mut level);
+        } else if event.0 == "graph" {
+            export_graph(&tiles, &level);
+        } else if event.0 == "plan" {
+            let mut level = level.clone();
+            level.tiles = tiles_to_tile_info(tiles.iter(), level.width, level.height);
+            match plan_routes(&level) {
+                Ok(plan) => info!(
+                    "Planned routes costing {} - {:?}",
+                    plan.cost, plan.placements
+                ),
+                Err(e) => error!("{e}"),
+            }
         } else if event.0 == "new" {
             for column in level.tiles.iter_mut() {
                 for mut tile in column.iter_mut() {
@@ -192,8 +242,18 @@ fn button_pressed(
                     tile.is_goal = false;
                     tile.contents = TileContents::None;
                     tile.cost_modifier = TileCostModifier::None;
+                    tile.water_capacity = None;
+                    tile.water_surface = None;
                 }
             }
+        } else if event.0 == "topology" {
+            // Keep any edits, since changing the level rebuilds the board
+            level.tiles = tiles_to_tile_info(tiles.iter(), level.width, level.height);
+            level.topology = match level.topology {
+                GridTopology::Square => GridTopology::Diagonal,
+                GridTopology::Diagonal => GridTopology::Hex,
+                GridTopology::Hex => GridTopology::Square,
+            };
         } else if event.0 == "width_add" {
             reset_tile_dimensions(level.width + 1, level.height, &mut level, &tiles);
         } else if event.0 == "width_sub" {
@@ -271,7 +331,13 @@ fn tile_clicked(
                     EditorOperation::ToggleConstruction(t) => {
                         let is_wet = matches!(
                             t,
-                            TileContents::Canal | TileContents::Lock | TileContents::Aquaduct(_)
+                            TileContents::Canal
+                                | TileContents::Lock
+                                | TileContents::Aquaduct(_)
+                                | TileContents::Reservoir
+                                | TileContents::PumpingStation
+                                | TileContents::Tunnel(_)
+                                | TileContents::Bridge
                         );
                         new_tile.wetness = if is_wet {
                             Wetness::WaterSource
@@ -298,6 +364,9 @@ fn tile_clicked(
                     EditorOperation::SetCostModifier(t) => {
                         new_tile.cost_modifier = t;
                     }
+                    EditorOperation::SetWaterCapacity(c) => {
+                        new_tile.water_capacity = c;
+                    }
                 }
             }
         }
@@ -347,6 +416,10 @@ fn tile_hovered_set(
                                 TileContents::Canal
                                     | TileContents::Lock
                                     | TileContents::Aquaduct(_)
+                                    | TileContents::Reservoir
+                                    | TileContents::PumpingStation
+                                    | TileContents::Tunnel(_)
+                                    | TileContents::Bridge
                             );
                             new_tile.wetness = if is_wet {
                                 Wetness::WaterSource
@@ -401,6 +474,17 @@ fn tile_hovered_set(
                 }
             }
         }
+        EditorOperation::SetWaterCapacity(c) => {
+            if buttons.pressed(MouseButton::Left) {
+                for event in events.iter() {
+                    if let TileEvent::HoverStarted(_old_tile, entity) = event {
+                        if let Ok(mut new_tile) = tiles.get_mut(*entity) {
+                            new_tile.water_capacity = c;
+                        }
+                    }
+                }
+            }
+        }
         _ => (),
     }
 }
@@ -422,6 +506,8 @@ fn tiles_to_tile_info<'a, T: Iterator<Item = &'a Tile>>(
                 info.tile_type = tile.tile_type;
                 info.contents = tile.contents;
                 info.cost_modifier = tile.cost_modifier;
+                info.water_capacity = tile.water_capacity;
+                info.water_surface = tile.water_surface;
             }
         }
     }
@@ -434,14 +520,57 @@ fn save(tiles: &Query<&Tile>, level: &Level) {
 
     let mut level = level.clone();
     level.tiles = tiles;
+    level.version = LEVEL_VERSION;
 
     let mut path = FileAssetIo::get_base_path();
     path.push("temporary_levels");
     path.push("edited_level.lvl.json");
 
     if let Ok(json) = serde_json::to_string_pretty(&level) {
-        if let Ok(mut file) = std::fs::File::create(path) {
+        if let Ok(mut file) = std::fs::File::create(&path) {
             let _ = write!(&mut file, "{json}");
         }
     }
+
+    path.set_file_name("edited_level.lvl.ron");
+    if let Ok(ron) = to_compact_string(&level) {
+        if let Ok(mut file) = std::fs::File::create(path) {
+            let _ = write!(&mut file, "{ron}");
+        }
+    }
+}
+
+/// Replaces the board with `temporary_levels/import.asc` or `temporary_levels/import.png`,
+/// squeezed onto the board's current size.
+fn import(level: &mut Level) {
+    let mut path = FileAssetIo::get_base_path();
+    path.push("temporary_levels");
+    path.push("import.asc");
+    if !path.exists() {
+        path.set_extension("png");
+    }
+
+    let settings = ImportSettings {
+        width: level.width,
+        height: level.height,
+        ..Default::default()
+    };
+    match read_elevations(&path) {
+        Ok(elevations) => level.tiles = elevations.to_level(None, &settings).tiles,
+        Err(e) => error!("{}: {e}", path.display()),
+    }
+}
+
+fn export_graph(tiles: &Query<&Tile>, level: &Level) {
+    let solution = WaterNetwork::new(level.width, level.height, tiles.iter())
+        .with_topology(level.topology)
+        .solve();
+
+    let mut path = FileAssetIo::get_base_path();
+    path.push("temporary_levels");
+    path.push("edited_level.dot");
+
+    if let Ok(mut file) = std::fs::File::create(path) {
+        let _ = write!(&mut file, "{}", solution.graph().to_dot());
+    }
 }
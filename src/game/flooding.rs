@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::IntoConditionalSystem;
+
+use super::{
+    board::*,
+    game_state::{GameResources, GameState},
+    level::Level,
+};
+
+pub struct FloodingPlugin;
+
+impl Plugin for FloodingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Flooding>().add_system(
+            pay_flood_compensation
+                .run_in_state(GameState::InGame)
+                .after("water_simulation"),
+        );
+    }
+}
+
+/// The land currently under water, and what the level has paid out for it so far.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Flooding {
+    pub tiles: Vec<(usize, usize)>,
+    /// Every tile that's been paid for, whether it's still under water or not.
+    pub paid: Vec<(usize, usize)>,
+    pub compensation: usize,
+}
+
+/// Pays the owners the first time their land floods - land that dries out and floods again with
+/// the tide has already been paid for.
+pub(super) fn pay_flood_compensation(
+    level: Res<Level>,
+    tiles: Query<&Tile, Changed<Tile>>,
+    mut flooding: ResMut<Flooding>,
+    mut resources: ResMut<GameResources>,
+) {
+    if level.is_changed() {
+        *flooding = Flooding::default();
+    }
+    for tile in tiles.iter() {
+        let position = (tile.x, tile.y);
+        let known = flooding.tiles.contains(&position);
+        if tile.flooded && !known {
+            flooding.tiles.push(position);
+            if !flooding.paid.contains(&position) {
+                let compensation = tile.get_flood_compensation();
+                flooding.paid.push(position);
+                flooding.compensation += compensation;
+                resources.cost_so_far += compensation;
+            }
+        } else if !tile.flooded && known {
+            flooding.tiles.retain(|p| *p != position);
+        }
+    }
+}
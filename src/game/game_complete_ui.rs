@@ -6,21 +6,50 @@ use iyes_loopless::state::NextState;
 use crate::app_state::*;
 use crate::ui::*;
 
+use super::boats::BoatTraffic;
 use super::game_state::GameResources;
 use super::game_state::GameState;
 use super::level::*;
+use super::ratings::{stars, BestRatings, CurrentLevelFile, MAX_STARS};
+use super::simulation::PlayTime;
+use super::trade::TollLedger;
 
 pub struct GameCompleteUiPlugin;
 
 impl Plugin for GameCompleteUiPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         clear_ui_system_set(app, GameState::Complete)
+            .insert_resource(BestRatings::load())
+            .init_resource::<CurrentLevelFile>()
             .add_enter_system(GameState::Complete, display_ui)
+            .add_system(update_labels.run_in_state(GameState::Complete))
             .add_system(button_pressed.run_in_state(GameState::Complete));
     }
 }
 
-fn display_ui(mut commands: Commands, resource: Res<GameResources>, level: Res<Level>) {
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+enum CompleteUiId {
+    EarningsText,
+    ProfitText,
+    TrafficText,
+}
+
+fn display_ui(
+    mut commands: Commands,
+    resource: Res<GameResources>,
+    level: Res<Level>,
+    traffic: Res<BoatTraffic>,
+    ledger: Res<TollLedger>,
+    play_time: Res<PlayTime>,
+    file: Res<CurrentLevelFile>,
+    mut ratings: ResMut<BestRatings>,
+) {
+    let par = level.par;
+    let stars = par.map(|par| stars(resource.cost_so_far, par));
+    if let (Some(file), Some(stars)) = (&file.0, stars) {
+        ratings.record(file, stars);
+    }
+
     commands
         .ui_root()
         .position(Val::Px(0.), Val::Px(0.), Val::Px(0.), Val::Auto)
@@ -34,10 +63,20 @@ fn display_ui(mut commands: Commands, resource: Res<GameResources>, level: Res<L
                     ))
                     .size(100.)
                     .style(FontStyle::Italic);
-                parent.text(format!(
-                    "The canal cost you {} Pounds",
-                    resource.cost_so_far
-                ));
+                parent
+                    .text(earnings_text(&resource))
+                    .id(CompleteUiId::EarningsText);
+                if let (Some(par), Some(stars)) = (par, stars) {
+                    parent.text(format!(
+                        "Par was {par} Pounds - you earned {stars} of {MAX_STARS} stars"
+                    ));
+                }
+                parent
+                    .text(profit_text(&resource, &ledger, play_time.0))
+                    .id(CompleteUiId::ProfitText);
+                parent
+                    .text(traffic_text(&traffic))
+                    .id(CompleteUiId::TrafficText);
             });
         });
     commands
@@ -54,6 +93,61 @@ fn display_ui(mut commands: Commands, resource: Res<GameResources>, level: Res<L
         });
 }
 
+/// The boats carry on once the level's complete, so the tolls keep coming in.
+fn update_labels(
+    mut labels: Query<(&mut GameText, &UiId<CompleteUiId>)>,
+    resource: Res<GameResources>,
+    traffic: Res<BoatTraffic>,
+    ledger: Res<TollLedger>,
+    play_time: Res<PlayTime>,
+) {
+    if !resource.is_changed() && !traffic.is_changed() {
+        return;
+    }
+    for (mut label, id) in labels.iter_mut() {
+        label.text(match id.val() {
+            CompleteUiId::EarningsText => earnings_text(&resource),
+            CompleteUiId::ProfitText => profit_text(&resource, &ledger, play_time.0),
+            CompleteUiId::TrafficText => traffic_text(&traffic),
+        });
+    }
+}
+
+fn earnings_text(resource: &GameResources) -> String {
+    format!(
+        "The canal cost you {} Pounds, and has earned {} Pounds in tolls",
+        resource.cost_so_far, resource.revenue
+    )
+}
+
+fn profit_text(resource: &GameResources, ledger: &TollLedger, now: f32) -> String {
+    let profit = resource.profit();
+    if profit >= 0 {
+        format!("A profit of {profit} Pounds")
+    } else {
+        match ledger.payback_minutes(resource, now) {
+            Some(minutes) => format!(
+                "A loss of {} Pounds so far - it will pay for itself in {minutes:.1} minutes",
+                -profit
+            ),
+            None => format!(
+                "A loss of {} Pounds so far - and no tolls coming in to pay it back",
+                -profit
+            ),
+        }
+    }
+}
+
+fn traffic_text(traffic: &BoatTraffic) -> String {
+    match traffic.average_journey_time() {
+        Some(journey_time) => format!(
+            "Boats carried {} loads, taking {journey_time:.0}s a journey",
+            traffic.deliveries.len()
+        ),
+        None => "The first boats are setting off".to_string(),
+    }
+}
+
 fn button_pressed(mut events: EventReader<ButtonClickEvent>, mut commands: Commands) {
     for event in events.iter() {
         if event.0 == "menu" {
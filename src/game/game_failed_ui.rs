@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::AppLooplessStateExt;
+use iyes_loopless::prelude::IntoConditionalSystem;
+use iyes_loopless::state::NextState;
+
+use crate::app_state::*;
+use crate::ui::*;
+
+use super::game_state::GameResources;
+use super::game_state::GameState;
+use super::level::*;
+
+pub struct GameFailedUiPlugin;
+
+impl Plugin for GameFailedUiPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        clear_ui_system_set(app, GameState::Failed)
+            .add_enter_system(GameState::Failed, display_ui)
+            .add_system(button_pressed.run_in_state(GameState::Failed));
+    }
+}
+
+fn display_ui(mut commands: Commands, resource: Res<GameResources>, level: Res<Level>) {
+    commands
+        .ui_root()
+        .position(Val::Px(0.), Val::Px(0.), Val::Px(0.), Val::Auto)
+        .for_state(GameState::Failed)
+        .with_children(|parent| {
+            parent.div().opaque().padding(5.).with_children(|parent| {
+                parent
+                    .text(format!(
+                        "{} Failed",
+                        level.title.as_ref().unwrap_or(&"Level".into())
+                    ))
+                    .size(100.)
+                    .style(FontStyle::Italic);
+                let budget = resource.budget.unwrap_or_default();
+                parent.text(if resource.over_budget() {
+                    format!(
+                        "The canal cost you {} Pounds - {} more than the {budget} Pounds you had",
+                        resource.cost_so_far,
+                        resource.cost_so_far - budget
+                    )
+                } else {
+                    format!(
+                        "With only {} of your {budget} Pounds left, there's nothing more you can afford to build",
+                        resource.remaining().unwrap_or_default()
+                    )
+                });
+            });
+        });
+    commands
+        .ui_root()
+        .position(Val::Px(0.), Val::Px(0.), Val::Auto, Val::Px(0.))
+        .for_state(GameState::Failed)
+        .with_children(|parent| {
+            parent.div().opaque().padding(5.).with_children(|parent| {
+                parent.button("retry", "Retry");
+                parent
+                    .button("level", "Choose Level")
+                    .style(ButtonStyle::Secondary);
+            });
+        });
+}
+
+fn button_pressed(
+    mut events: EventReader<ButtonClickEvent>,
+    mut commands: Commands,
+    mut level: ResMut<Level>,
+) {
+    for event in events.iter() {
+        if event.0 == "retry" {
+            // Marking the level changed rebuilds the board and sets its events up again
+            level.set_changed();
+            commands.insert_resource(GameResources::default());
+            commands.insert_resource(NextState(GameState::Setup));
+        } else if event.0 == "level" {
+            commands.insert_resource(NextState(AppState::ChooseLevel));
+        }
+    }
+}
@@ -13,6 +13,7 @@ pub enum GameState {
     InGame,
     Editor,
     Complete,
+    Failed,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Reflect, FromReflect)]
@@ -21,12 +22,35 @@ pub enum GameActionMode {
     DigCanal,
     ConstructLock,
     BuildAquaduct,
+    BuildReservoir,
+    BuildPumpingStation,
+    BuildTunnel,
+    DigCutting,
+    BuildEmbankment,
     Demolish,
 }
 
 #[derive(Resource, Debug, Default, Clone, Reflect)]
 pub struct GameResources {
     pub cost_so_far: usize,
+    pub revenue: usize,
+    pub budget: Option<usize>,
+}
+
+impl GameResources {
+    pub fn profit(&self) -> isize {
+        self.revenue as isize - self.cost_so_far as isize
+    }
+
+    /// What's left to spend, if the level has a budget.
+    pub fn remaining(&self) -> Option<usize> {
+        self.budget
+            .map(|budget| budget.saturating_sub(self.cost_so_far))
+    }
+
+    pub fn over_budget(&self) -> bool {
+        matches!(self.budget, Some(budget) if self.cost_so_far > budget)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -34,5 +58,41 @@ pub enum GameActions {
     DigCanal(Tile),
     ConstructLock(Tile),
     BuildAquaduct(Tile, usize),
+    BuildReservoir(Tile),
+    BuildPumpingStation(Tile),
+    BuildTunnel(Tile, usize),
+    DigCutting(Tile),
+    BuildEmbankment(Tile),
     Demolish(Tile),
 }
+
+impl GameActions {
+    /// The tool the action was taken with.
+    pub fn mode(&self) -> GameActionMode {
+        match self {
+            GameActions::DigCanal(_) => GameActionMode::DigCanal,
+            GameActions::ConstructLock(_) => GameActionMode::ConstructLock,
+            GameActions::BuildAquaduct(_, _) => GameActionMode::BuildAquaduct,
+            GameActions::BuildReservoir(_) => GameActionMode::BuildReservoir,
+            GameActions::BuildPumpingStation(_) => GameActionMode::BuildPumpingStation,
+            GameActions::BuildTunnel(_, _) => GameActionMode::BuildTunnel,
+            GameActions::DigCutting(_) => GameActionMode::DigCutting,
+            GameActions::BuildEmbankment(_) => GameActionMode::BuildEmbankment,
+            GameActions::Demolish(_) => GameActionMode::Demolish,
+        }
+    }
+
+    pub fn tile(&self) -> &Tile {
+        match self {
+            GameActions::DigCanal(tile)
+            | GameActions::ConstructLock(tile)
+            | GameActions::BuildAquaduct(tile, _)
+            | GameActions::BuildReservoir(tile)
+            | GameActions::BuildPumpingStation(tile)
+            | GameActions::BuildTunnel(tile, _)
+            | GameActions::DigCutting(tile)
+            | GameActions::BuildEmbankment(tile)
+            | GameActions::Demolish(tile) => tile,
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use bevy::prelude::Vec3;
+use noisy_bevy::simplex_noise_3d;
+
+use super::{
+    board::{TileContents, TileCostModifier, TileType, Topology},
+    level::{Level, LevelTools, TileInfo},
+    level_loader::LEVEL_VERSION,
+    route::RouteMap,
+    solver::plan_routes,
+};
+
+/// How big a generated level is, how hilly, and how many goals it has.
+#[derive(Debug, Clone)]
+pub struct GeneratorSettings {
+    pub width: usize,
+    pub height: usize,
+    pub max_height: usize,
+    pub goals: usize,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            width: 12,
+            height: 12,
+            max_height: 6,
+            goals: 2,
+        }
+    }
+}
+
+/// Goals closer to the water than this many placements make for a dull puzzle, so they're only
+/// used if there's nowhere further away to put them.
+const MIN_ROUTE_LENGTH: usize = 4;
+
+/// The share of the lowest ground that's left as sea.
+const SEA: f32 = 0.1;
+
+/// Samples one layer of the noise for a seed - each layer is its own slice through the noise,
+/// so the terrain, towns and costs don't line up with one another.
+fn noise(seed: u32, layer: usize, x: usize, y: usize, scale: f32) -> f32 {
+    simplex_noise_3d(Vec3::new(
+        x as f32 * scale,
+        y as f32 * scale,
+        seed as f32 * 1.618 + layer as f32 * 17.3,
+    ))
+}
+
+/// Where each tile's noise falls amongst the rest of the board's, from 0 (lowest) to 1 (highest),
+/// so the share of sea, towns and costs stays the same whatever the noise happens to do.
+fn ranks(seed: u32, layers: &[(usize, f32, f32)], width: usize, height: usize) -> Vec<Vec<f32>> {
+    let values = (0..width)
+        .map(|x| {
+            (0..height)
+                .map(|y| {
+                    layers
+                        .iter()
+                        .map(|(layer, scale, weight)| noise(seed, *layer, x, y, *scale) * weight)
+                        .sum::<f32>()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut sorted = values.iter().flatten().copied().collect::<Vec<_>>();
+    sorted.sort_by(f32::total_cmp);
+    let count = (sorted.len().max(2) - 1) as f32;
+    values
+        .into_iter()
+        .map(|column| {
+            column
+                .into_iter()
+                .map(|value| sorted.partition_point(|v| *v < value) as f32 / count)
+                .collect()
+        })
+        .collect()
+}
+
+/// Builds a level from a seed - the same seed always gives the same level.
+/// Goals are only put where a route search, going the way water flows, can carry the water to
+/// them - and par is only set if the route planner finds a way to join them all up.
+pub fn generate_level(seed: u32, settings: &GeneratorSettings) -> Level {
+    let (width, height) = (settings.width, settings.height);
+    let mut tiles = vec![vec![TileInfo::default(); height]; width];
+
+    let elevations = ranks(seed, &[(0, 0.15, 0.7), (1, 0.4, 0.3)], width, height);
+    let towns = ranks(seed, &[(2, 0.3, 1.)], width, height);
+    let costs = ranks(seed, &[(3, 0.5, 1.)], width, height);
+
+    for (x, column) in tiles.iter_mut().enumerate() {
+        for (y, tile) in column.iter_mut().enumerate() {
+            let elevation = elevations[x][y];
+            if elevation < SEA {
+                tile.tile_type = TileType::Sea;
+                continue;
+            }
+            tile.height = (((elevation - SEA) / (1. - SEA) * (settings.max_height + 1) as f32)
+                as usize)
+                .min(settings.max_height);
+
+            tile.tile_type = if towns[x][y] > 0.92 {
+                TileType::City
+            } else if towns[x][y] > 0.75 {
+                TileType::Farm
+            } else {
+                TileType::Land
+            };
+
+            tile.cost_modifier = if costs[x][y] > 0.95 {
+                TileCostModifier::Blocked
+            } else if costs[x][y] < 0.08 {
+                TileCostModifier::Multiplier
+            } else {
+                TileCostModifier::None
+            };
+        }
+    }
+
+    let mut level = Level {
+        version: LEVEL_VERSION,
+        tiles,
+        title: Some(format!("Random Level #{seed}")),
+        initial_description: Some(
+            "A stretch of countryside no one has built a canal across yet. Bring water to every goal."
+                .into(),
+        ),
+        sidebar_text: None,
+        width,
+        height,
+        events: vec![],
+        tools: LevelTools::planned(),
+        roads: vec![],
+        places: vec![],
+        water_cycle: Default::default(),
+        topology: Default::default(),
+        par: None,
+        budget: None,
+    };
+    run_river(&mut level);
+    place_goals(seed, settings.goals, &mut level);
+    // The river never runs dry, so the planner won't refuse it for a source that can
+    level.par = plan_routes(&level).ok().map(|plan| plan.cost);
+    level
+}
+
+/// Runs a river downhill from the highest ground, until it reaches the sea or can't go any lower.
+fn run_river(level: &mut Level) {
+    let mut current = (0..level.width)
+        .flat_map(|x| (0..level.height).map(move |y| (x, y)))
+        .max_by_key(|(x, y)| level.tiles[*x][*y].height);
+
+    while let Some((x, y)) = current {
+        let tile = &mut level.tiles[x][y];
+        if tile.tile_type == TileType::Sea {
+            break;
+        }
+        tile.contents = TileContents::River;
+        tile.cost_modifier = TileCostModifier::None;
+        let height = tile.height;
+
+        current = level
+            .topology
+            .neighbours(x, y, level.width, level.height)
+            .into_iter()
+            .flatten()
+            .filter(|(x, y)| {
+                let tile = &level.tiles[*x][*y];
+                tile.contents != TileContents::River && tile.height <= height
+            })
+            .min_by_key(|(x, y)| level.tiles[*x][*y].height);
+    }
+}
+
+/// Puts goals where a route search can reach them - each one from the water, or from the
+/// routes to the goals before it, so they all end up joined to the same water.
+fn place_goals(seed: u32, goals: usize, level: &mut Level) {
+    let mut candidates = (0..level.width)
+        .flat_map(|x| (0..level.height).map(move |y| (x, y)))
+        .filter(|(x, y)| {
+            let tile = &level.tiles[*x][*y];
+            tile.tile_type != TileType::Sea
+                && tile.contents == TileContents::None
+                && tile.cost_modifier != TileCostModifier::Blocked
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|(ax, ay), (bx, by)| {
+        noise(seed, 4, *bx, *by, 0.9).total_cmp(&noise(seed, 4, *ax, *ay, 0.9))
+    });
+
+    let mut map = RouteMap::new(level);
+    let mut connected = map.sources();
+    let mut placed = 0;
+    for min_length in [MIN_ROUTE_LENGTH, 1] {
+        for (x, y) in candidates.iter().copied() {
+            if placed == goals {
+                return;
+            }
+            if level.tiles[x][y].is_goal {
+                continue;
+            }
+            if let Some(route) = map.cheapest_route(&connected, (x, y)) {
+                if route.placements.len() < min_length {
+                    continue;
+                }
+                level.tiles[x][y].is_goal = true;
+                map.build(&route.placements);
+                connected.extend(route.placements.iter().map(|p| (p.x, p.y)));
+                placed += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate_level, GeneratorSettings};
+    use crate::game::{
+        level::LevelTools, level_loader::validate, simulation::WaterNetwork, solver::plan_routes,
+    };
+
+    #[test]
+    fn seeds_always_give_the_same_level() {
+        let settings = GeneratorSettings::default();
+        let a = serde_json::to_value(generate_level(42, &settings)).unwrap();
+        let b = serde_json::to_value(generate_level(42, &settings)).unwrap();
+        let c = serde_json::to_value(generate_level(43, &settings)).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn generated_goals_fill_with_the_planned_routes() {
+        let settings = GeneratorSettings::default();
+        for seed in 0..20 {
+            let mut level = generate_level(seed, &settings);
+            assert!(validate("generated", &level).is_empty());
+            let plan = plan_routes(&level).unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+            assert_eq!(level.par, Some(plan.cost), "seed {seed}");
+            // Par only holds if the player has no tools the planner goes without
+            assert_eq!(
+                serde_json::to_value(&level.tools).unwrap(),
+                serde_json::to_value(LevelTools::planned()).unwrap()
+            );
+
+            for placement in plan.placements.iter() {
+                let tile = &mut level.tiles[placement.x][placement.y];
+                tile.contents = placement.contents;
+                tile.water_surface = None;
+            }
+            let tiles = (0..level.width)
+                .flat_map(|x| (0..level.height).map(move |y| (x, y)))
+                .map(|(x, y)| level.tiles[x][y].to_tile(x, y))
+                .collect::<Vec<_>>();
+            let solution = WaterNetwork::new(level.width, level.height, tiles.iter())
+                .with_topology(level.topology)
+                .solve();
+
+            let goals = tiles.iter().filter(|tile| tile.is_goal).collect::<Vec<_>>();
+            assert!(!goals.is_empty(), "seed {seed} has no goals");
+            let components = goals
+                .iter()
+                .map(|goal| solution.graph().component_at(goal.x, goal.y))
+                .collect::<Vec<_>>();
+            for (goal, component) in goals.iter().zip(components.iter()) {
+                assert!(
+                    matches!(component, Some(component) if !component.sources.is_empty()),
+                    "seed {seed} leaves ({}, {}) dry",
+                    goal.x,
+                    goal.y
+                );
+            }
+            assert!(
+                components.iter().all(|c| *c == components[0]),
+                "seed {seed} fills its goals from different water"
+            );
+        }
+    }
+}
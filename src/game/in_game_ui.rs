@@ -4,8 +4,13 @@ use iyes_loopless::prelude::*;
 use crate::{app_state::AppState, assets::CanalManiaAssets, ui::*};
 
 use super::{
+    boats::BoatTraffic,
+    flooding::Flooding,
     game_state::{GameActionMode, GameResources, GameState},
     level::{Level, LevelTools},
+    roads::SeveredRoads,
+    simulation::PlayTime,
+    water_cycle::{WaterCycle, WaterLevels},
 };
 
 pub struct InGameUiPlugin;
@@ -25,11 +30,21 @@ impl Plugin for InGameUiPlugin {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 enum GameUiId {
     CostText,
+    RevenueText,
+    TrafficText,
+    RoadsText,
+    WaterLevelText,
+    FloodText,
     SidebarText,
     Sidebar,
     Dig,
     Lock,
     Aquaduct,
+    Reservoir,
+    PumpingStation,
+    Tunnel,
+    Cutting,
+    Embankment,
     Demolish,
 }
 
@@ -44,6 +59,12 @@ fn display_ui(
     sidebar: Res<SidebarText>,
     resources: Res<GameResources>,
     operation: Res<CurrentState<GameActionMode>>,
+    traffic: Res<BoatTraffic>,
+    play_time: Res<PlayTime>,
+    severed: Res<SeveredRoads>,
+    cycle: Res<WaterCycle>,
+    levels: Res<WaterLevels>,
+    flooding: Res<Flooding>,
 ) {
     commands
         .ui_root()
@@ -59,10 +80,33 @@ fn display_ui(
                     .icon(asset.coin_icon.clone())
                     .size(GameIconSize::Normal);
                 parent
-                    .text(resources.cost_so_far.to_string())
+                    .text(cost_text(&resources))
                     .size(20.)
                     .id(GameUiId::CostText);
+                parent
+                    .icon(asset.coins_icon.clone())
+                    .size(GameIconSize::Normal);
+                parent
+                    .text(resources.revenue.to_string())
+                    .size(20.)
+                    .id(GameUiId::RevenueText);
             });
+            parent
+                .text(traffic_text(&traffic, play_time.0))
+                .size(15.)
+                .id(GameUiId::TrafficText);
+            parent
+                .text(severed_text(&severed))
+                .size(15.)
+                .id(GameUiId::RoadsText);
+            parent
+                .text(water_level_text(&cycle, &levels))
+                .size(15.)
+                .id(GameUiId::WaterLevelText);
+            parent
+                .text(flood_text(&flooding))
+                .size(15.)
+                .id(GameUiId::FloodText);
         });
 
     commands
@@ -96,6 +140,56 @@ fn display_ui(
                     .hidden(!tools.aquaduct)
                     .selected(operation.0 == GameActionMode::BuildAquaduct)
                     .icon(asset.aqueduct_icon.clone());
+                parent
+                    .button(
+                        "reservoir",
+                        "Build Reservoir\nStore water to feed the canals around it.\nOnly holds enough for a few locks.",
+                    )
+                    .id(GameUiId::Reservoir)
+                    .style(ButtonStyle::Action)
+                    .hidden(!tools.reservoir)
+                    .selected(operation.0 == GameActionMode::BuildReservoir)
+                    .icon(asset.reservoir_icon.clone());
+                parent
+                    .button(
+                        "pumping-station",
+                        "Build Pumping Station\nLift water up from the canal below it.\nCosts a little to keep running.",
+                    )
+                    .id(GameUiId::PumpingStation)
+                    .style(ButtonStyle::Action)
+                    .hidden(!tools.pumping_station)
+                    .selected(operation.0 == GameActionMode::BuildPumpingStation)
+                    .icon(asset.pumping_station_icon.clone());
+                parent
+                    .button(
+                        "tunnel",
+                        "Dig Tunnel\nCarry a canal under high ground.\nThe deeper it goes, the more it costs.",
+                    )
+                    .id(GameUiId::Tunnel)
+                    .style(ButtonStyle::Action)
+                    .hidden(!tools.tunnel)
+                    .selected(operation.0 == GameActionMode::BuildTunnel)
+                    .icon(asset.tunnel_icon.clone());
+                parent
+                    .button(
+                        "cutting",
+                        "Dig Cutting\nLower the ground to bring it level with a canal.\nThe deeper it goes, the more it costs.",
+                    )
+                    .id(GameUiId::Cutting)
+                    .style(ButtonStyle::Action)
+                    .hidden(!tools.cutting)
+                    .selected(operation.0 == GameActionMode::DigCutting)
+                    .icon(asset.cutting_icon.clone());
+                parent
+                    .button(
+                        "embankment",
+                        "Build Embankment\nRaise the ground to bring it level with a canal.\nThe higher it goes, the more it costs.",
+                    )
+                    .id(GameUiId::Embankment)
+                    .style(ButtonStyle::Action)
+                    .hidden(!tools.embankment)
+                    .selected(operation.0 == GameActionMode::BuildEmbankment)
+                    .icon(asset.embankment_icon.clone());
                 parent
                     .button("demolish", "Demolish\nMade a mistake? Demolish it.")
                     .id(GameUiId::Demolish)
@@ -154,6 +248,12 @@ fn update_labels(
     mut buttons: Query<(&mut GameButton, &UiId<GameUiId>)>,
     operation: Res<CurrentState<GameActionMode>>,
     resources: Res<GameResources>,
+    traffic: Res<BoatTraffic>,
+    play_time: Res<PlayTime>,
+    severed: Res<SeveredRoads>,
+    cycle: Res<WaterCycle>,
+    levels: Res<WaterLevels>,
+    flooding: Res<Flooding>,
 ) {
     if operation.is_changed() {
         for (mut button, id) in buttons.iter_mut() {
@@ -161,6 +261,11 @@ fn update_labels(
                 GameUiId::Dig => operation.0 == GameActionMode::DigCanal,
                 GameUiId::Lock => operation.0 == GameActionMode::ConstructLock,
                 GameUiId::Aquaduct => operation.0 == GameActionMode::BuildAquaduct,
+                GameUiId::Reservoir => operation.0 == GameActionMode::BuildReservoir,
+                GameUiId::PumpingStation => operation.0 == GameActionMode::BuildPumpingStation,
+                GameUiId::Tunnel => operation.0 == GameActionMode::BuildTunnel,
+                GameUiId::Cutting => operation.0 == GameActionMode::DigCutting,
+                GameUiId::Embankment => operation.0 == GameActionMode::BuildEmbankment,
                 GameUiId::Demolish => operation.0 == GameActionMode::Demolish,
                 _ => {
                     continue;
@@ -171,11 +276,95 @@ fn update_labels(
     }
     if resources.is_changed() {
         for (mut label, id) in labels.iter_mut() {
-            if let GameUiId::CostText = id.val() {
-                label.text(resources.cost_so_far.to_string());
+            match id.val() {
+                GameUiId::CostText => {
+                    label.text(cost_text(&resources));
+                }
+                GameUiId::RevenueText => {
+                    label.text(resources.revenue.to_string());
+                }
+                _ => {}
             }
         }
     }
+    if traffic.is_changed() {
+        for (mut label, id) in labels.iter_mut() {
+            if let GameUiId::TrafficText = id.val() {
+                label.text(traffic_text(&traffic, play_time.0));
+            }
+        }
+    }
+    if severed.is_changed() {
+        for (mut label, id) in labels.iter_mut() {
+            if let GameUiId::RoadsText = id.val() {
+                label.text(severed_text(&severed));
+            }
+        }
+    }
+    if cycle.is_changed() || levels.is_changed() {
+        for (mut label, id) in labels.iter_mut() {
+            if let GameUiId::WaterLevelText = id.val() {
+                label.text(water_level_text(&cycle, &levels));
+            }
+        }
+    }
+    if flooding.is_changed() {
+        for (mut label, id) in labels.iter_mut() {
+            if let GameUiId::FloodText = id.val() {
+                label.text(flood_text(&flooding));
+            }
+        }
+    }
+}
+
+fn flood_text(flooding: &Flooding) -> String {
+    match (flooding.tiles.len(), flooding.compensation) {
+        (0, 0) => String::new(),
+        (0, paid) => format!("Paid {paid} in flood compensation"),
+        (tiles, paid) => {
+            format!("{tiles} tiles are flooded - paid {paid} in compensation, it needs a lock")
+        }
+    }
+}
+
+fn water_level_text(cycle: &WaterCycle, levels: &WaterLevels) -> String {
+    if cycle.is_static() {
+        return String::new();
+    }
+    let mut text = vec![];
+    if cycle.tide_range > 0 {
+        text.push(format!("Tide: {} of {}", levels.tide, cycle.tide_range));
+    }
+    if cycle.tidal_rivers || cycle.season_range > 0 {
+        text.push(format!("Rivers: {:+}", levels.river));
+    }
+    text.join(" - ")
+}
+
+fn cost_text(resources: &GameResources) -> String {
+    match resources.budget {
+        Some(budget) => format!("{} of {budget}", resources.cost_so_far),
+        None => resources.cost_so_far.to_string(),
+    }
+}
+
+fn severed_text(severed: &SeveredRoads) -> String {
+    severed
+        .0
+        .iter()
+        .map(|name| format!("You've cut off the {name} - it needs a bridge"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn traffic_text(traffic: &BoatTraffic, now: f32) -> String {
+    match traffic.average_journey_time() {
+        Some(journey_time) => format!(
+            "{:.1} boats a minute, taking {journey_time:.0}s a journey",
+            traffic.boats_per_minute(now)
+        ),
+        None => "No boats have made a journey yet".to_string(),
+    }
 }
 
 fn update_sidebar(
@@ -213,6 +402,11 @@ fn update_buttons(
                 GameUiId::Dig => tools.canal,
                 GameUiId::Lock => tools.lock,
                 GameUiId::Aquaduct => tools.aquaduct,
+                GameUiId::Reservoir => tools.reservoir,
+                GameUiId::PumpingStation => tools.pumping_station,
+                GameUiId::Tunnel => tools.tunnel,
+                GameUiId::Cutting => tools.cutting,
+                GameUiId::Embankment => tools.embankment,
                 GameUiId::Demolish => tools.demolish,
                 _ => true,
             };
@@ -232,6 +426,16 @@ fn button_pressed(mut events: EventReader<ButtonClickEvent>, mut commands: Comma
             commands.insert_resource(NextState(GameActionMode::ConstructLock));
         } else if event.0 == "aquaduct" {
             commands.insert_resource(NextState(GameActionMode::BuildAquaduct));
+        } else if event.0 == "reservoir" {
+            commands.insert_resource(NextState(GameActionMode::BuildReservoir));
+        } else if event.0 == "pumping-station" {
+            commands.insert_resource(NextState(GameActionMode::BuildPumpingStation));
+        } else if event.0 == "tunnel" {
+            commands.insert_resource(NextState(GameActionMode::BuildTunnel));
+        } else if event.0 == "cutting" {
+            commands.insert_resource(NextState(GameActionMode::DigCutting));
+        } else if event.0 == "embankment" {
+            commands.insert_resource(NextState(GameActionMode::BuildEmbankment));
         } else if event.0 == "demolish" {
             commands.insert_resource(NextState(GameActionMode::Demolish));
         } else if event.0 == "choose-level" {
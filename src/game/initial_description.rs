@@ -6,6 +6,7 @@ use iyes_loopless::state::NextState;
 use crate::ui::*;
 
 use super::game_state::GameState;
+use super::level::{DialogueChoice, EventAction};
 
 pub struct InitialDescriptionUiPlugin;
 
@@ -13,6 +14,7 @@ impl Plugin for InitialDescriptionUiPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         clear_ui_system_set(app, GameState::Description)
             .init_resource::<CurrentDescription>()
+            .add_event::<ChoiceMade>()
             .add_enter_system(GameState::Description, display_ui)
             .add_system(button_pressed.run_in_state(GameState::Description));
     }
@@ -23,8 +25,13 @@ pub struct CurrentDescription {
     pub title: Option<String>,
     pub text: Option<String>,
     pub continue_button: Option<String>,
+    /// When there are choices, there's a button for each of them in place of the continue button.
+    pub choices: Vec<DialogueChoice>,
 }
 
+/// The player picked one of a dialogue's choices, which costs this much and runs these actions.
+pub struct ChoiceMade(pub usize, pub Vec<EventAction>);
+
 fn display_ui(mut commands: Commands, current_description: Res<CurrentDescription>) {
     if !current_description.is_changed() {
         return;
@@ -41,21 +48,45 @@ fn display_ui(mut commands: Commands, current_description: Res<CurrentDescriptio
                     parent.text(description);
                     parent.div().padding(5.);
 
-                    let button_text = current_description
-                        .continue_button
-                        .as_ref()
-                        .unwrap_or(&"Continue".to_string())
-                        .clone();
-                    parent.button("play", button_text);
+                    if current_description.choices.is_empty() {
+                        let button_text = current_description
+                            .continue_button
+                            .as_ref()
+                            .unwrap_or(&"Continue".to_string())
+                            .clone();
+                        parent.button("play", button_text);
+                    }
+                    for (index, choice) in current_description.choices.iter().enumerate() {
+                        let label = if choice.cost > 0 {
+                            format!("{} ({} Pounds)", choice.label, choice.cost)
+                        } else {
+                            choice.label.clone()
+                        };
+                        parent.button(format!("choice:{index}"), label);
+                    }
                 });
             });
     }
 }
 
-fn button_pressed(mut events: EventReader<ButtonClickEvent>, mut commands: Commands) {
+fn button_pressed(
+    mut events: EventReader<ButtonClickEvent>,
+    mut commands: Commands,
+    current_description: Res<CurrentDescription>,
+    mut choices: EventWriter<ChoiceMade>,
+) {
     for event in events.iter() {
         if event.0 == "play" {
             commands.insert_resource(NextState(GameState::InGame));
+        } else if let Some(index) = event.0.strip_prefix("choice:") {
+            let choice = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| current_description.choices.get(index));
+            if let Some(choice) = choice {
+                choices.send(ChoiceMade(choice.cost, choice.actions.clone()));
+                commands.insert_resource(NextState(GameState::InGame));
+            }
         }
     }
 }
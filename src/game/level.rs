@@ -1,16 +1,19 @@
-use std::collections::VecDeque;
+use std::collections::HashSet;
 
 use bevy::{prelude::*, reflect::TypeUuid};
 use serde::{Deserialize, Serialize};
 
 use super::{
-    board::{TileContents, TileCostModifier, TileType},
+    board::{GridTopology, Tile, TileContents, TileCostModifier, TileType, Wetness},
     game_state::GameActionMode,
+    water_cycle::WaterCycle,
 };
 
-#[derive(Resource, Component, Serialize, Deserialize, TypeUuid, Clone)]
-#[uuid = "b9b5565a-a06a-4647-bc62-274f32ba6a5f"]
+#[derive(Resource, Component, Serialize, Deserialize, Clone, Debug)]
 pub struct Level {
+    /// The version of the level format the file was written in - see `LEVEL_VERSION`.
+    #[serde(default)]
+    pub version: u32,
     pub tiles: Vec<Vec<TileInfo>>,
     pub title: Option<String>,
     pub initial_description: Option<String>,
@@ -19,8 +22,52 @@ pub struct Level {
     pub height: usize,
     #[serde(default)]
     pub events: Vec<LevelEvent>,
-    #[serde(default)]
+    /// Levels that don't list their tools only get the ones the game started out with.
+    #[serde(default = "LevelTools::unlisted")]
     pub tools: LevelTools,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roads: Vec<NamedRoad>,
+    /// Towns and landmarks, labelled on the board and named by event actions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub places: Vec<NamedPlace>,
+    #[serde(default, skip_serializing_if = "WaterCycle::is_static")]
+    pub water_cycle: WaterCycle,
+    /// Which tiles count as next to one another - square boards unless the level says otherwise.
+    #[serde(default, skip_serializing_if = "GridTopology::is_square")]
+    pub topology: GridTopology,
+    /// What the level should cost to complete, for full marks - levels without one aren't
+    /// rated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub par: Option<usize>,
+    /// The most the level lets you spend - going over it, or running too low to build anything
+    /// more, fails the level. Levels without one can spend as much as they like.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<usize>,
+}
+
+/// A road the level needs kept open between two of its tiles.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NamedRoad {
+    pub name: String,
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+/// A tile the level gives a name to.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NamedPlace {
+    pub name: String,
+    pub position: (usize, usize),
+}
+
+impl Level {
+    /// Where the named place is, if the level has one by that name.
+    pub fn place(&self, name: &str) -> Option<(usize, usize)> {
+        self.places
+            .iter()
+            .find(|place| place.name == name)
+            .map(|place| place.position)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -35,7 +82,7 @@ pub struct LevelList {
     pub levels: Vec<LevelListing>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Default, Reflect)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Reflect)]
 pub struct TileInfo {
     #[serde(default)]
     pub tile_type: TileType,
@@ -46,6 +93,34 @@ pub struct TileInfo {
     #[serde(default)]
     pub cost_modifier: TileCostModifier,
     pub height: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub water_capacity: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub water_surface: Option<usize>,
+}
+
+impl TileInfo {
+    /// The tile as it starts out at (x, y) - rivers and the sea hold water from the beginning.
+    pub fn to_tile(&self, x: usize, y: usize) -> Tile {
+        Tile {
+            x,
+            y,
+            z: self.height,
+            tile_type: self.tile_type,
+            is_goal: self.is_goal,
+            contents: self.contents,
+            wetness: if self.contents == TileContents::River || self.tile_type == TileType::Sea {
+                Wetness::WaterSource
+            } else {
+                Wetness::Dry
+            },
+            cost_modifier: self.cost_modifier,
+            water_capacity: self.water_capacity,
+            water_surface: self.water_surface,
+            flooded: false,
+            earthworks: 0,
+        }
+    }
 }
 
 #[derive(Resource, Clone, Debug, Serialize, Deserialize, Reflect)]
@@ -53,31 +128,311 @@ pub struct LevelTools {
     pub canal: bool,
     pub lock: bool,
     pub aquaduct: bool,
+    #[serde(default)]
+    pub reservoir: bool,
+    #[serde(default)]
+    pub pumping_station: bool,
+    #[serde(default)]
+    pub tunnel: bool,
+    #[serde(default)]
+    pub cutting: bool,
+    #[serde(default)]
+    pub embankment: bool,
     pub demolish: bool,
 }
 
+impl LevelTools {
+    /// The tools a level gets when it doesn't list any - just the ones the game started out with.
+    pub fn unlisted() -> Self {
+        Self {
+            canal: true,
+            lock: true,
+            aquaduct: true,
+            reservoir: false,
+            pumping_station: false,
+            tunnel: false,
+            cutting: false,
+            embankment: false,
+            demolish: true,
+        }
+    }
+
+    /// The tools the route planner builds with, so a level giving the player just these can
+    /// have its par planned for it.
+    pub fn planned() -> Self {
+        Self {
+            canal: true,
+            lock: true,
+            aquaduct: true,
+            reservoir: false,
+            pumping_station: false,
+            tunnel: false,
+            cutting: false,
+            embankment: false,
+            demolish: true,
+        }
+    }
+
+    /// Makes the tool for an action available, or takes it away.
+    pub fn set(&mut self, mode: GameActionMode, available: bool) {
+        match mode {
+            GameActionMode::None => {}
+            GameActionMode::DigCanal => self.canal = available,
+            GameActionMode::ConstructLock => self.lock = available,
+            GameActionMode::BuildAquaduct => self.aquaduct = available,
+            GameActionMode::BuildReservoir => self.reservoir = available,
+            GameActionMode::BuildPumpingStation => self.pumping_station = available,
+            GameActionMode::BuildTunnel => self.tunnel = available,
+            GameActionMode::DigCutting => self.cutting = available,
+            GameActionMode::BuildEmbankment => self.embankment = available,
+            GameActionMode::Demolish => self.demolish = available,
+        }
+    }
+
+    /// The least any of these tools would cost to build on the tile, if any of them can.
+    /// Demolishing isn't counted, since it doesn't get the water anywhere.
+    pub fn cheapest_on(&self, tile: &Tile) -> Option<usize> {
+        [
+            (self.canal, tile.get_canal_cost()),
+            (self.lock, tile.get_lock_cost()),
+            (self.aquaduct, tile.get_aquaduct_cost()),
+            (self.reservoir, tile.get_reservoir_cost()),
+            (self.pumping_station, tile.get_pumping_station_cost()),
+            (self.tunnel, tile.get_tunnel_cost(1)),
+            (self.cutting, tile.get_cutting_cost()),
+            (self.embankment, tile.get_embankment_cost()),
+        ]
+        .into_iter()
+        .filter_map(|(available, cost)| if available { cost } else { None })
+        .min()
+    }
+}
+
 impl Default for LevelTools {
     fn default() -> Self {
         Self {
             canal: true,
             lock: true,
             aquaduct: true,
+            reservoir: true,
+            pumping_station: true,
+            tunnel: true,
+            cutting: true,
+            embankment: true,
             demolish: true,
         }
     }
 }
 
+/// The events still to fire. They wait their turn in the order the level lists them, apart from
+/// those marked `armed`, which can fire at any point.
+#[derive(Clone, Debug, Resource, Default)]
+pub struct PendingLevelEvents(Vec<PendingLevelEvent>);
+
+#[derive(Clone, Debug)]
+struct PendingLevelEvent {
+    event: LevelEvent,
+    /// Where the event is in the level's list.
+    index: usize,
+    armed: bool,
+    /// Whether the trigger was met when it was last checked, so repeating events only fire
+    /// again once it's been unmet in between.
+    met: bool,
+}
+
+impl PendingLevelEvents {
+    pub fn new(events: &[LevelEvent]) -> Self {
+        Self(
+            events
+                .iter()
+                .enumerate()
+                .map(|(index, event)| PendingLevelEvent {
+                    event: event.clone(),
+                    index,
+                    armed: event.2.armed,
+                    met: false,
+                })
+                .collect(),
+        )
+    }
+
+    /// The events that can fire right now - the armed ones, and the first in the queue.
+    pub fn armed(&self) -> impl Iterator<Item = &LevelEvent> {
+        let next = self.0.iter().position(|pending| !pending.armed);
+        self.0
+            .iter()
+            .enumerate()
+            .filter(move |(index, pending)| pending.armed || Some(*index) == next)
+            .map(|(_, pending)| &pending.event)
+    }
+
+    /// Where the first armed event waiting on the trigger is in the level's list.
+    pub fn waiting_on(&self, trigger: &LevelEventType) -> Option<usize> {
+        let next = self.0.iter().position(|pending| !pending.armed);
+        self.0
+            .iter()
+            .enumerate()
+            .find(|(index, pending)| {
+                (pending.armed || Some(*index) == next) && pending.event.0 == *trigger
+            })
+            .map(|(_, pending)| pending.index)
+    }
+
+    /// Takes the armed events whose triggers are met, highest priority first. `met` says
+    /// whether a trigger is met, or `None` for triggers the caller doesn't keep track of.
+    ///
+    /// Repeating events stay armed after firing - even ones that were waiting in the queue, which
+    /// moves on without them.
+    pub fn fire(&mut self, met: impl Fn(&LevelEventType) -> Option<bool>) -> Vec<LevelEvent> {
+        let next = self.0.iter().position(|pending| !pending.armed);
+        let mut fired = vec![];
+        for (index, pending) in self.0.iter_mut().enumerate() {
+            if !pending.armed && Some(index) != next {
+                continue;
+            }
+            let is_met = match met(&pending.event.0) {
+                Some(is_met) => is_met,
+                None => continue,
+            };
+            let was_met = std::mem::replace(&mut pending.met, is_met);
+            if is_met && (!was_met || pending.event.0.is_moment()) {
+                fired.push(index);
+            }
+        }
+        fired.sort_by_key(|index| std::cmp::Reverse(self.0[*index].event.2.priority));
+
+        let events = fired
+            .iter()
+            .map(|index| self.0[*index].event.clone())
+            .collect();
+        let mut index = 0;
+        self.0.retain_mut(|pending| {
+            let fired = fired.contains(&index);
+            index += 1;
+            if fired && pending.event.2.repeat {
+                pending.armed = true;
+            }
+            !fired || pending.event.2.repeat
+        });
+        events
+    }
+}
+
+/// Flags set by level events, for later events to check.
 #[derive(Clone, Debug, Resource, Default)]
-pub struct PendingLevelEvents(pub VecDeque<LevelEvent>);
+pub struct LevelFlags(pub HashSet<String>);
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct LevelEvent(pub LevelEventType, pub Vec<EventAction>);
+pub struct LevelEvent(
+    pub LevelEventType,
+    pub Vec<EventAction>,
+    #[serde(default, skip_serializing_if = "EventOptions::is_default")] pub EventOptions,
+);
+
+/// How an event waits to fire, and whether it fires more than once.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EventOptions {
+    /// Armed from the start of the level, rather than waiting for the events before it.
+    #[serde(default)]
+    pub armed: bool,
+    /// Fires every time its trigger is met, rather than just the first.
+    #[serde(default)]
+    pub repeat: bool,
+    /// When several events fire together, the higher priorities go first.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl EventOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One of the answers to a dialogue, and what happens when the player picks it.
+#[derive(Clone, Debug, Serialize, Deserialize, Reflect, FromReflect)]
+pub struct DialogueChoice {
+    pub label: String,
+    /// What picking the choice costs, added to the level's costs.
+    #[serde(default)]
+    pub cost: usize,
+    #[serde(default)]
+    pub actions: Vec<EventAction>,
+}
+
+/// A rectangle of tiles, between two opposite corners.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Reflect, FromReflect)]
+pub struct Region {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+impl Region {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        let (left, right) = (self.from.0.min(self.to.0), self.from.0.max(self.to.0));
+        let (top, bottom) = (self.from.1.min(self.to.1), self.from.1.max(self.to.1));
+        (left..=right).contains(&x) && (top..=bottom).contains(&y)
+    }
+
+    pub fn positions(&self) -> Vec<(usize, usize)> {
+        let (left, right) = (self.from.0.min(self.to.0), self.from.0.max(self.to.0));
+        let (top, bottom) = (self.from.1.min(self.to.1), self.from.1.max(self.to.1));
+        (left..=right)
+            .flat_map(|x| (top..=bottom).map(move |y| (x, y)))
+            .collect()
+    }
+}
+
+/// The tiles an action applies to - a whole region, a list of them, or the level's places
+/// with these names.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Reflect, FromReflect)]
+pub enum TileSelection {
+    Region(Region),
+    List(Vec<(usize, usize)>),
+    Places(Vec<String>),
+}
+
+impl TileSelection {
+    /// The selected tiles - any names the level doesn't have a place for are skipped.
+    pub fn positions(&self, level: &Level) -> Vec<(usize, usize)> {
+        match self {
+            TileSelection::Region(region) => region.positions(),
+            TileSelection::List(positions) => positions.clone(),
+            TileSelection::Places(names) => {
+                names.iter().filter_map(|name| level.place(name)).collect()
+            }
+        }
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Reflect)]
 pub enum LevelEventType {
     GoalReached,
     AnyActionsComplete(usize, bool),
     BuiltNofType(usize, GameActionMode, bool),
+    /// Once boats have carried at least this many loads, optionally only from or to a given tile.
+    LoadsDelivered(usize, Option<(usize, usize)>, Option<(usize, usize)>),
+    /// Once water reaches the tile.
+    TileWet(usize, usize),
+    /// Once the level has cost more than this.
+    CostAbove(usize),
+    /// Once the level has been played for this many seconds.
+    TimeElapsed(usize),
+    /// When the tool is used anywhere in the region.
+    ToolUsedInRegion(GameActionMode, Region),
+    /// Once an event has set the flag.
+    FlagSet(String),
+}
+
+impl LevelEventType {
+    /// Whether the trigger is something that happens, rather than something that holds - those
+    /// fire on every occurrence when repeated, rather than waiting to stop being met first.
+    pub fn is_moment(&self) -> bool {
+        matches!(
+            self,
+            LevelEventType::GoalReached | LevelEventType::ToolUsedInRegion(_, _)
+        )
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Reflect, FromReflect)]
@@ -87,10 +442,117 @@ pub enum EventAction {
         title: Option<String>,
         continue_button: Option<String>,
     },
+    /// Text with a button for each of the choices, instead of one to continue - the choice the
+    /// player picks runs its own actions.
+    Dialogue {
+        text: String,
+        title: Option<String>,
+        choices: Vec<DialogueChoice>,
+    },
     SetSidebar(Option<String>),
     SetNewGoal(usize, usize),
     AdjustCost(usize, usize, TileCostModifier),
     AdjustContents(usize, usize, TileContents),
     SetHeight(usize, usize, usize),
+    /// The same as `SetNewGoal`, `AdjustCost`, `AdjustContents` and `SetHeight`, for every tile
+    /// in the selection at once.
+    SetNewGoalsIn(TileSelection),
+    AdjustCostIn(TileSelection, TileCostModifier),
+    AdjustContentsIn(TileSelection, TileContents),
+    SetHeightIn(TileSelection, usize),
+    /// Turns the tiles into springs that water flows out of, whatever is on them.
+    MakeWaterSource(TileSelection),
+    /// Takes away every goal still on the board.
+    ClearGoals,
     AdjustToolAccess(GameActionMode, bool),
+    SetWaterCycle(WaterCycle),
+    /// Adds to the level's budget - shareholders investing once they've seen some progress.
+    GrantFunds(usize),
+    /// Takes from the level's budget, as far as there's any left.
+    WithdrawFunds(usize),
+    SetFlag(String),
+    ClearFlag(String),
+    CompleteLevel,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EventOptions, LevelEvent, LevelEventType, PendingLevelEvents};
+
+    fn event(trigger: LevelEventType, options: EventOptions) -> LevelEvent {
+        LevelEvent(trigger, vec![], options)
+    }
+
+    fn triggers(events: Vec<LevelEvent>) -> Vec<LevelEventType> {
+        events.into_iter().map(|event| event.0).collect()
+    }
+
+    #[test]
+    fn armed_events_fire_out_of_turn() {
+        let mut pending = PendingLevelEvents::new(&[
+            event(LevelEventType::GoalReached, Default::default()),
+            event(LevelEventType::CostAbove(10), Default::default()),
+            event(
+                LevelEventType::CostAbove(20),
+                EventOptions {
+                    armed: true,
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let cost_above_20 = |trigger: &LevelEventType| match trigger {
+            LevelEventType::CostAbove(cost) => Some(*cost < 25),
+            _ => None,
+        };
+
+        // The second event is still waiting for the goal
+        assert_eq!(
+            triggers(pending.fire(cost_above_20)),
+            vec![LevelEventType::CostAbove(20)]
+        );
+        assert_eq!(
+            triggers(pending.fire(|trigger| Some(*trigger == LevelEventType::GoalReached))),
+            vec![LevelEventType::GoalReached]
+        );
+        assert_eq!(
+            triggers(pending.fire(cost_above_20)),
+            vec![LevelEventType::CostAbove(10)]
+        );
+        assert_eq!(pending.armed().count(), 0);
+    }
+
+    #[test]
+    fn repeating_events_fire_again_once_unmet() {
+        let repeat = EventOptions {
+            repeat: true,
+            ..Default::default()
+        };
+        let mut pending = PendingLevelEvents::new(&[
+            event(LevelEventType::FlagSet("low".into()), repeat.clone()),
+            event(
+                LevelEventType::FlagSet("high".into()),
+                EventOptions {
+                    priority: 1,
+                    ..repeat
+                },
+            ),
+        ]);
+
+        // The queue moves on past the first event, and the higher priority goes first
+        assert_eq!(pending.fire(|_| Some(true)).len(), 1);
+        assert_eq!(
+            triggers(pending.fire(|_| Some(true))),
+            vec![LevelEventType::FlagSet("high".into())]
+        );
+        assert!(pending.fire(|_| Some(true)).is_empty());
+
+        pending.fire(|_| Some(false));
+        assert_eq!(
+            triggers(pending.fire(|_| Some(true))),
+            vec![
+                LevelEventType::FlagSet("high".into()),
+                LevelEventType::FlagSet("low".into())
+            ]
+        );
+    }
 }
@@ -0,0 +1,347 @@
+use std::{fmt::Display, path::Path};
+
+use super::{
+    board::TileType,
+    compact_level::to_compact_string,
+    level::{Level, LevelTools, TileInfo},
+    level_loader::LEVEL_VERSION,
+};
+
+/// Ground elevations read from a heightmap or an elevation grid, row by row from the north.
+/// Cells without any data are `None`, and end up as sea.
+#[derive(Debug, Clone)]
+pub struct Elevations {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<Option<f32>>,
+}
+
+/// How an elevation map gets squeezed onto a board.
+#[derive(Debug, Clone)]
+pub struct ImportSettings {
+    pub width: usize,
+    pub height: usize,
+    /// The land is spread over heights 1 to this - the highest ground in the map ends up here.
+    pub max_height: usize,
+    /// Anything at or below this elevation is sea. Heightmaps run from 0 (black) to 1 (white),
+    /// while elevation grids use whatever units the data was recorded in.
+    pub sea_level: f32,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            width: 20,
+            height: 20,
+            max_height: 5,
+            sea_level: 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    Unreadable(String),
+    /// An elevation grid is missing one of its header lines.
+    MissingHeader(&'static str),
+    /// An elevation grid has a different number of values than its header promised.
+    WrongSize {
+        expected: usize,
+        found: usize,
+    },
+    Usage(String),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Unreadable(reason) => write!(f, "Couldn't read the map - {reason}"),
+            ImportError::MissingHeader(key) => {
+                write!(f, "The elevation grid doesn't say what its {key} is")
+            }
+            ImportError::WrongSize { expected, found } => write!(
+                f,
+                "The elevation grid should have {expected} values, but has {found}"
+            ),
+            ImportError::Usage(problem) => write!(
+                f,
+                "{problem}\nUsage: canal-mania import <map.png|map.asc> <level.lvl.json|level.lvl.ron> [--size WxH] [--max-height N] [--sea-level X]"
+            ),
+        }
+    }
+}
+
+/// Reads a grayscale heightmap, with black as the lowest ground and white as the highest.
+/// Transparent pixels have no data.
+pub fn read_heightmap(bytes: &[u8]) -> Result<Elevations, ImportError> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| ImportError::Unreadable(e.to_string()))?
+        .into_luma_alpha16();
+    let values = image
+        .pixels()
+        .map(|pixel| {
+            let [luma, alpha] = pixel.0;
+            (alpha > 0).then_some(luma as f32 / u16::MAX as f32)
+        })
+        .collect();
+    Ok(Elevations {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        values,
+    })
+}
+
+/// Reads an ESRI ASCII grid - a few `key value` header lines, followed by the elevations
+/// themselves.
+pub fn read_esri_ascii(text: &str) -> Result<Elevations, ImportError> {
+    let mut tokens = text.split_whitespace().peekable();
+    let mut header = vec![];
+    while let Some(key) = tokens.next_if(|t| t.parse::<f32>().is_err()) {
+        let value = tokens
+            .next()
+            .and_then(|v| v.parse::<f32>().ok())
+            .ok_or_else(|| ImportError::Unreadable(format!("{key} has no value")))?;
+        header.push((key.to_lowercase(), value));
+    }
+    let get = |key| {
+        header
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| *value)
+    };
+    let width = get("ncols").ok_or(ImportError::MissingHeader("ncols"))? as usize;
+    let height = get("nrows").ok_or(ImportError::MissingHeader("nrows"))? as usize;
+    let no_data = get("nodata_value");
+
+    let values = tokens
+        .map(|t| {
+            t.parse::<f32>()
+                .map(|value| (Some(value) != no_data).then_some(value))
+                .map_err(|e| ImportError::Unreadable(format!("{t} - {e}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.len() != width * height {
+        return Err(ImportError::WrongSize {
+            expected: width * height,
+            found: values.len(),
+        });
+    }
+    Ok(Elevations {
+        width,
+        height,
+        values,
+    })
+}
+
+impl Elevations {
+    /// The average elevation of the cells that fall within tile (x, y) of a board this size.
+    fn sample(&self, x: usize, y: usize, width: usize, height: usize) -> Option<f32> {
+        let span = |i: usize, from: usize, to: usize| {
+            let start = i * from / to;
+            start..((i + 1) * from / to).max(start + 1)
+        };
+        let columns = span(x, self.width, width);
+        let rows = span(y, self.height, height);
+        let cells = rows
+            .flat_map(|row| columns.clone().map(move |column| (column, row)))
+            .filter_map(|(column, row)| *self.values.get(row * self.width + column)?)
+            .collect::<Vec<_>>();
+        if cells.is_empty() {
+            None
+        } else {
+            Some(cells.iter().sum::<f32>() / cells.len() as f32)
+        }
+    }
+
+    /// Turns the elevations into a level, with everything at or below sea level as sea.
+    pub fn to_level(&self, title: Option<String>, settings: &ImportSettings) -> Level {
+        let samples = (0..settings.width)
+            .map(|x| {
+                (0..settings.height)
+                    .map(|y| self.sample(x, y, settings.width, settings.height))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let highest = samples
+            .iter()
+            .flatten()
+            .flatten()
+            .fold(settings.sea_level, |a, b| a.max(*b));
+        let range = highest - settings.sea_level;
+
+        let tiles = samples
+            .into_iter()
+            .map(|column| {
+                column
+                    .into_iter()
+                    .map(|elevation| match elevation {
+                        Some(elevation) if elevation > settings.sea_level => TileInfo {
+                            height: (((elevation - settings.sea_level) / range
+                                * settings.max_height as f32)
+                                .ceil() as usize)
+                                .clamp(1, settings.max_height.max(1)),
+                            ..Default::default()
+                        },
+                        _ => TileInfo {
+                            tile_type: TileType::Sea,
+                            ..Default::default()
+                        },
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Level {
+            version: LEVEL_VERSION,
+            tiles,
+            title,
+            initial_description: None,
+            sidebar_text: None,
+            width: settings.width,
+            height: settings.height,
+            events: vec![],
+            tools: LevelTools::planned(),
+            roads: vec![],
+            places: vec![],
+            water_cycle: Default::default(),
+            topology: Default::default(),
+            par: None,
+            budget: None,
+        }
+    }
+}
+
+/// Reads a heightmap, or an elevation grid if the file ends in `.asc`.
+pub fn read_elevations(path: &Path) -> Result<Elevations, ImportError> {
+    let bytes = std::fs::read(path).map_err(|e| ImportError::Unreadable(e.to_string()))?;
+    if path.to_string_lossy().ends_with(".asc") {
+        read_esri_ascii(&String::from_utf8_lossy(&bytes))
+    } else {
+        read_heightmap(&bytes)
+    }
+}
+
+/// Imports a map from the command line -
+/// `canal-mania import <map> <level> [--size WxH] [--max-height N] [--sea-level X]`.
+/// The level is written in the compact format if its name ends in `.lvl.ron`.
+pub fn run_command(args: &[String]) -> Result<(), ImportError> {
+    let usage = |problem: &str| ImportError::Usage(problem.to_string());
+    let mut settings = ImportSettings::default();
+    let mut files = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name| {
+            args.next()
+                .ok_or_else(|| usage(&format!("{name} needs a value")))
+        };
+        match arg.as_str() {
+            "--size" => {
+                let size = value("--size")?;
+                let (width, height) = size
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .ok_or_else(|| usage(&format!("{size} isn't a size like 20x20")))?;
+                settings.width = width;
+                settings.height = height;
+            }
+            "--max-height" => {
+                let max_height = value("--max-height")?;
+                settings.max_height = max_height
+                    .parse()
+                    .map_err(|_| usage(&format!("{max_height} isn't a height")))?;
+            }
+            "--sea-level" => {
+                let sea_level = value("--sea-level")?;
+                settings.sea_level = sea_level
+                    .parse()
+                    .map_err(|_| usage(&format!("{sea_level} isn't an elevation")))?;
+            }
+            file => files.push(file),
+        }
+    }
+    let (source, destination) = match files[..] {
+        [source, destination] => (source, destination),
+        _ => return Err(usage("Expected a map to import and a level to write")),
+    };
+
+    let source = Path::new(source);
+    let title = source
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string());
+    let level = read_elevations(source)?.to_level(title, &settings);
+    let contents = if destination.ends_with(".lvl.ron") {
+        to_compact_string(&level).map_err(|e| ImportError::Unreadable(e.to_string()))?
+    } else {
+        serde_json::to_string_pretty(&level).map_err(|e| ImportError::Unreadable(e.to_string()))?
+    };
+    std::fs::write(destination, contents).map_err(|e| ImportError::Unreadable(e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_esri_ascii, read_heightmap, ImportError, ImportSettings};
+    use crate::game::board::TileType;
+
+    const GRID: &str = "ncols 4
+        nrows 2
+        xllcorner 0
+        yllcorner 0
+        cellsize 50
+        NODATA_value -9999
+        -9999 0 10 20
+        5 10 40 80";
+
+    #[test]
+    fn elevation_grids_are_scaled_onto_the_board() {
+        let elevations = read_esri_ascii(GRID).unwrap();
+        assert_eq!(elevations.values[0], None);
+
+        let settings = ImportSettings {
+            width: 2,
+            height: 2,
+            max_height: 4,
+            sea_level: 0.,
+        };
+        let level = elevations.to_level(None, &settings);
+        // Imported levels can be given par by the route planner, so they only get its tools
+        assert!(level.tools.canal && !level.tools.pumping_station);
+        // The no data cell is skipped, leaving only sea level
+        assert_eq!(level.tiles[0][0].tile_type, TileType::Sea);
+        assert_eq!(level.tiles[0][1].height, 1);
+        assert_eq!(level.tiles[1][1].height, 4);
+
+        let settings = ImportSettings {
+            sea_level: 10.,
+            ..settings
+        };
+        let level = elevations.to_level(None, &settings);
+        assert_eq!(level.tiles[0][0].tile_type, TileType::Sea);
+        assert_eq!(level.tiles[0][1].tile_type, TileType::Sea);
+        assert_eq!(level.tiles[1][0].height, 1);
+    }
+
+    #[test]
+    fn short_grids_are_refused() {
+        let short = GRID.trim_end_matches(" 80");
+        assert_eq!(
+            read_esri_ascii(short).unwrap_err(),
+            ImportError::WrongSize {
+                expected: 8,
+                found: 7
+            }
+        );
+    }
+
+    #[test]
+    fn heightmaps_run_from_black_to_white() {
+        let mut bytes = vec![];
+        image::DynamicImage::ImageLuma8(image::GrayImage::from_raw(2, 1, vec![0, 255]).unwrap())
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        let elevations = read_heightmap(&bytes).unwrap();
+        assert_eq!(elevations.values, vec![Some(0.), Some(1.)]);
+    }
+}
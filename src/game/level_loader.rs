@@ -0,0 +1,415 @@
+use std::fmt::Display;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde_json::Value;
+
+use super::level::{EventAction, Level, LevelEventType, TileSelection};
+
+/// The version of the level format this build writes - older files are migrated up to it as
+/// they load.
+pub const LEVEL_VERSION: u32 = 1;
+
+/// Upgrades a level from the version at its index to the next one.
+const MIGRATIONS: [fn(&mut Value); LEVEL_VERSION as usize] = [tools_are_explicit];
+
+/// Levels from before versioning only listed the tools that existed when they were written -
+/// anything added since stays switched off. Those that didn't list any had all of them, and
+/// still do.
+fn tools_are_explicit(level: &mut Value) {
+    if let Some(level) = level.as_object_mut() {
+        level.entry("tools").or_insert_with(
+            || serde_json::json!({"canal": true, "lock": true, "aquaduct": true, "demolish": true}),
+        );
+    }
+    if let Some(tools) = level.get_mut("tools").and_then(Value::as_object_mut) {
+        for tool in [
+            "reservoir",
+            "pumping_station",
+            "tunnel",
+            "cutting",
+            "embankment",
+        ] {
+            tools.entry(tool).or_insert(Value::Bool(false));
+        }
+    }
+}
+
+/// A level file as it came off the disk - either a level that's ready to play, or everything
+/// that's wrong with it.
+#[derive(TypeUuid, Debug)]
+#[uuid = "b9b5565a-a06a-4647-bc62-274f32ba6a5f"]
+pub struct LevelFile(pub Result<Level, Vec<LevelError>>);
+
+/// Something in a level file that would stop it playing properly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelError {
+    pub file: String,
+    /// The index of the event at fault, if it's one of the level's events.
+    pub event: Option<usize>,
+    /// The tile at fault, if there is one.
+    pub position: Option<(usize, usize)>,
+    pub problem: LevelProblem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LevelProblem {
+    Unreadable(String),
+    /// The file was written by a newer version of the game.
+    TooNew(u32),
+    WrongWidth {
+        width: usize,
+        columns: usize,
+    },
+    WrongHeight {
+        height: usize,
+        tiles: usize,
+    },
+    OffTheBoard,
+    /// A compact level's layer has a symbol that doesn't stand for anything.
+    UnknownSymbol(String, char),
+    /// An event names a place the level doesn't have.
+    UnknownPlace(String),
+    /// An event grants or withdraws funds, but the level has no budget for them to change.
+    FundsWithoutBudget,
+}
+
+impl Display for LevelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(event) = self.event {
+            write!(f, ", event {event}")?;
+        }
+        if let Some((x, y)) = self.position {
+            write!(f, ", tile ({x}, {y})")?;
+        }
+        match &self.problem {
+            LevelProblem::Unreadable(reason) => write!(f, ": couldn't be read - {reason}"),
+            LevelProblem::TooNew(version) => write!(
+                f,
+                ": is version {version}, but only version {LEVEL_VERSION} is supported"
+            ),
+            LevelProblem::WrongWidth { width, columns } => {
+                write!(f, ": is {width} wide, but has {columns} columns of tiles")
+            }
+            LevelProblem::WrongHeight { height, tiles } => {
+                write!(f, ": is {height} high, but this column has {tiles} tiles")
+            }
+            LevelProblem::OffTheBoard => write!(f, ": is off the edge of the board"),
+            LevelProblem::UnknownSymbol(layer, symbol) => {
+                write!(
+                    f,
+                    ": has '{symbol}' in its {layer}, which isn't a known symbol"
+                )
+            }
+            LevelProblem::UnknownPlace(name) => {
+                write!(f, ": names {name}, but the level has no place called that")
+            }
+            LevelProblem::FundsWithoutBudget => {
+                write!(
+                    f,
+                    ": changes the level's funds, but the level has no budget"
+                )
+            }
+        }
+    }
+}
+
+/// Reads a level, bringing it up to the current version and checking it over.
+pub fn load_level(file: &str, bytes: &[u8]) -> Result<Level, Vec<LevelError>> {
+    let error = |problem| {
+        vec![LevelError {
+            file: file.to_string(),
+            event: None,
+            position: None,
+            problem,
+        }]
+    };
+    let mut value = serde_json::from_slice::<Value>(bytes)
+        .map_err(|e| error(LevelProblem::Unreadable(e.to_string())))?;
+    migrate(&mut value).map_err(error)?;
+    let level = serde_json::from_value::<Level>(value)
+        .map_err(|e| error(LevelProblem::Unreadable(e.to_string())))?;
+
+    let errors = validate(file, &level);
+    if errors.is_empty() {
+        Ok(level)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Runs every migration the level is missing, leaving it at the current version.
+pub fn migrate(level: &mut Value) -> Result<(), LevelProblem> {
+    let version = level.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > LEVEL_VERSION {
+        return Err(LevelProblem::TooNew(version));
+    }
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(level);
+    }
+    if let Some(level) = level.as_object_mut() {
+        level.insert("version".into(), LEVEL_VERSION.into());
+    }
+    Ok(())
+}
+
+/// Everything wrong with a level, along with the event and tile each problem is at.
+pub fn validate(file: &str, level: &Level) -> Vec<LevelError> {
+    let mut problems = vec![];
+    let mut report = |event, position, problem| {
+        problems.push(LevelError {
+            file: file.to_string(),
+            event,
+            position,
+            problem,
+        })
+    };
+    if level.tiles.len() != level.width {
+        report(
+            None,
+            None,
+            LevelProblem::WrongWidth {
+                width: level.width,
+                columns: level.tiles.len(),
+            },
+        );
+    }
+    for (x, column) in level.tiles.iter().enumerate() {
+        if column.len() != level.height {
+            report(
+                None,
+                Some((x, 0)),
+                LevelProblem::WrongHeight {
+                    height: level.height,
+                    tiles: column.len(),
+                },
+            );
+        }
+    }
+
+    let on_board = |(x, y): (usize, usize)| x < level.width && y < level.height;
+    for road in level.roads.iter() {
+        for position in [road.from, road.to] {
+            if !on_board(position) {
+                report(None, Some(position), LevelProblem::OffTheBoard);
+            }
+        }
+    }
+    for place in level.places.iter() {
+        if !on_board(place.position) {
+            report(None, Some(place.position), LevelProblem::OffTheBoard);
+        }
+    }
+    for (index, event) in level.events.iter().enumerate() {
+        let mut positions = vec![];
+        match &event.0 {
+            LevelEventType::LoadsDelivered(_, from, to) => {
+                positions.extend(*from);
+                positions.extend(*to);
+            }
+            LevelEventType::TileWet(x, y) => positions.push((*x, *y)),
+            LevelEventType::ToolUsedInRegion(_, region) => {
+                positions.push(region.from);
+                positions.push(region.to);
+            }
+            _ => {}
+        }
+        let mut names = vec![];
+        for action in all_actions(&event.1) {
+            match action {
+                EventAction::SetNewGoal(x, y)
+                | EventAction::AdjustCost(x, y, _)
+                | EventAction::AdjustContents(x, y, _)
+                | EventAction::SetHeight(x, y, _) => positions.push((*x, *y)),
+                EventAction::SetNewGoalsIn(selection)
+                | EventAction::AdjustCostIn(selection, _)
+                | EventAction::AdjustContentsIn(selection, _)
+                | EventAction::SetHeightIn(selection, _)
+                | EventAction::MakeWaterSource(selection) => match selection {
+                    // The rest of a region is on the board as long as its corners are
+                    TileSelection::Region(region) => positions.extend([region.from, region.to]),
+                    TileSelection::List(list) => positions.extend(list),
+                    TileSelection::Places(places) => names.extend(places.iter().cloned()),
+                },
+                EventAction::GrantFunds(_) | EventAction::WithdrawFunds(_)
+                    if level.budget.is_none() =>
+                {
+                    report(Some(index), None, LevelProblem::FundsWithoutBudget);
+                }
+                _ => {}
+            }
+        }
+        for position in positions {
+            if !on_board(position) {
+                report(Some(index), Some(position), LevelProblem::OffTheBoard);
+            }
+        }
+        for name in names {
+            if level.place(&name).is_none() {
+                report(Some(index), None, LevelProblem::UnknownPlace(name));
+            }
+        }
+    }
+    problems
+}
+
+/// The actions, along with those behind any dialogue choices amongst them.
+fn all_actions(actions: &[EventAction]) -> Vec<&EventAction> {
+    let mut all = vec![];
+    for action in actions {
+        all.push(action);
+        if let EventAction::Dialogue { choices, .. } = action {
+            for choice in choices {
+                all.extend(all_actions(&choice.actions));
+            }
+        }
+    }
+    all
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let file = load_context.path().display().to_string();
+            load_context.set_default_asset(LoadedAsset::new(LevelFile(load_level(&file, bytes))));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lvl.json"]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load_level, LevelProblem, LEVEL_VERSION};
+
+    const LEVEL: &str = r#"{
+        "tiles": [[{"height": 0}, {"height": 1}], [{"height": 0}, {"height": 2}]],
+        "title": null,
+        "initial_description": null,
+        "sidebar_text": null,
+        "width": 2,
+        "height": 2,
+        "tools": {"canal": true, "lock": true, "aquaduct": false, "demolish": true},
+        "events": [[{"AnyActionsComplete": [0, false]}, [{"SetNewGoal": [1, 1]}]]]
+    }"#;
+
+    #[test]
+    fn unversioned_levels_are_migrated() {
+        let level = load_level("test", LEVEL.as_bytes()).unwrap();
+        assert_eq!(level.version, LEVEL_VERSION);
+        assert!(level.tools.canal);
+        assert!(!level.tools.tunnel);
+
+        let without_tools = LEVEL.replace(
+            r#""tools": {"canal": true, "lock": true, "aquaduct": false, "demolish": true},"#,
+            "",
+        );
+        let level = load_level("test", without_tools.as_bytes()).unwrap();
+        assert!(level.tools.aquaduct);
+        assert!(!level.tools.reservoir);
+    }
+
+    #[test]
+    fn shipped_levels_are_valid() {
+        let levels = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/levels"))
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.to_string_lossy().ends_with(".lvl.json"));
+        for path in levels {
+            let bytes = std::fs::read(&path).unwrap();
+            if let Err(errors) = load_level(&path.to_string_lossy(), &bytes) {
+                panic!("{}", errors[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn levels_from_newer_versions_are_refused() {
+        let newer = LEVEL.replacen('{', r#"{"version": 99,"#, 1);
+        let errors = load_level("test", newer.as_bytes()).unwrap_err();
+        assert_eq!(errors[0].problem, LevelProblem::TooNew(99));
+    }
+
+    #[test]
+    fn problems_point_at_their_event_and_tile() {
+        let broken = LEVEL
+            .replace(r#"[{"height": 0}, {"height": 2}]"#, r#"[{"height": 0}]"#)
+            .replace(r#""SetNewGoal": [1, 1]"#, r#""SetNewGoal": [3, 1]"#);
+        let errors = load_level("broken.lvl.json", broken.as_bytes()).unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].position, Some((1, 0)));
+        assert_eq!(
+            errors[0].problem,
+            LevelProblem::WrongHeight {
+                height: 2,
+                tiles: 1
+            }
+        );
+        assert_eq!(errors[1].event, Some(0));
+        assert_eq!(errors[1].position, Some((3, 1)));
+        assert_eq!(
+            errors[1].to_string(),
+            "broken.lvl.json, event 0, tile (3, 1): is off the edge of the board"
+        );
+    }
+
+    #[test]
+    fn dialogue_choices_are_checked_too() {
+        let dialogue = LEVEL.replace(
+            r#"{"SetNewGoal": [1, 1]}"#,
+            r#"{"Dialogue": {"text": "Pay up?", "title": null, "choices": [
+                {"label": "Pay", "cost": 20, "actions": [{"AdjustCost": [4, 0, "None"]}]},
+                {"label": "Route around"}
+            ]}}"#,
+        );
+        let errors = load_level("test", dialogue.as_bytes()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].position, Some((4, 0)));
+    }
+
+    #[test]
+    fn places_are_found_by_name() {
+        let named = LEVEL
+            .replacen(
+                '{',
+                r#"{"places": [{"name": "Sale", "position": [1, 1]}],"#,
+                1,
+            )
+            .replace(
+                r#"{"SetNewGoal": [1, 1]}"#,
+                r#"{"SetNewGoalsIn": {"Places": ["Sale", "Lymm"]}}"#,
+            );
+        let errors = load_level("test", named.as_bytes()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].problem, LevelProblem::UnknownPlace("Lymm".into()));
+
+        let level = load_level("test", named.replace(", \"Lymm\"", "").as_bytes()).unwrap();
+        assert_eq!(level.place("Sale"), Some((1, 1)));
+    }
+
+    #[test]
+    fn funds_need_a_budget() {
+        let grant = LEVEL.replace(r#"{"SetNewGoal": [1, 1]}"#, r#"{"GrantFunds": 20}"#);
+        let errors = load_level("test", grant.as_bytes()).unwrap_err();
+        assert_eq!(errors[0].problem, LevelProblem::FundsWithoutBudget);
+
+        let budgeted = grant.replacen('{', r#"{"budget": 40,"#, 1);
+        assert!(load_level("test", budgeted.as_bytes()).is_ok());
+    }
+}
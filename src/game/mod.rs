@@ -1,19 +1,39 @@
 mod board;
+mod boats;
+pub mod compact_level;
 mod game_state;
 mod in_game_ui;
 pub mod level;
+#[cfg(not(target_family = "wasm"))]
+pub mod level_import;
+pub mod level_loader;
 
 mod build_aquaduct;
+mod build_embankment;
+mod build_pumping_station;
+mod build_reservoir;
+mod build_tunnel;
 mod demolish;
 mod dig_canal;
+mod dig_cutting;
 mod dig_lock;
 #[cfg(not(target_family = "wasm"))]
 mod editor_ui;
+mod flooding;
 mod game_complete_ui;
+mod game_failed_ui;
+pub mod generator;
 mod initial_description;
+mod place_labels;
+pub mod ratings;
+mod roads;
+mod route;
 mod simulation;
+pub mod solver;
 mod tile_hover_ui;
 pub mod tile_shader;
+mod trade;
+mod water_cycle;
 
 use bevy::prelude::*;
 use iyes_loopless::{prelude::AppLooplessStateExt, state::NextState};
@@ -22,17 +42,29 @@ use crate::app_state::AppState;
 
 use self::{
     board::BoardPlugin,
+    boats::BoatsPlugin,
     build_aquaduct::BuildAquaductPlugin,
+    build_embankment::BuildEmbankmentPlugin,
+    build_pumping_station::BuildPumpingStationPlugin,
+    build_reservoir::BuildReservoirPlugin,
+    build_tunnel::BuildTunnelPlugin,
     demolish::DemolishPlugin,
     dig_canal::DigCanalPlugin,
+    dig_cutting::DigCuttingPlugin,
     dig_lock::DigLockPlugin,
+    flooding::FloodingPlugin,
     game_complete_ui::GameCompleteUiPlugin,
+    game_failed_ui::GameFailedUiPlugin,
     game_state::{GameActionMode, GameActions, GameResources, GameState},
     in_game_ui::InGameUiPlugin,
     initial_description::InitialDescriptionUiPlugin,
+    place_labels::PlaceLabelsPlugin,
+    roads::RoadsPlugin,
     simulation::SimulationPlugin,
     tile_hover_ui::TileHoverUi,
     tile_shader::TileMaterial,
+    trade::TradePlugin,
+    water_cycle::WaterCyclePlugin,
 };
 
 pub struct GamePlugin;
@@ -46,16 +78,29 @@ impl Plugin for GamePlugin {
             .add_enter_system(AppState::InGame, prepare_for_setup)
             .add_exit_system(AppState::InGame, prepare_for_setup)
             .add_enter_system(GameState::Complete, disable_actions)
+            .add_enter_system(GameState::Failed, disable_actions)
             .add_plugin(BoardPlugin)
             .add_plugin(TileHoverUi)
+            .add_plugin(PlaceLabelsPlugin)
             .add_plugin(InGameUiPlugin)
             .add_plugin(InitialDescriptionUiPlugin)
             .add_plugin(GameCompleteUiPlugin)
+            .add_plugin(GameFailedUiPlugin)
             .add_plugin(DigCanalPlugin)
             .add_plugin(DigLockPlugin)
             .add_plugin(DemolishPlugin)
             .add_plugin(BuildAquaductPlugin)
+            .add_plugin(BuildReservoirPlugin)
+            .add_plugin(BuildPumpingStationPlugin)
+            .add_plugin(BuildTunnelPlugin)
+            .add_plugin(DigCuttingPlugin)
+            .add_plugin(BuildEmbankmentPlugin)
             .add_plugin(SimulationPlugin)
+            .add_plugin(BoatsPlugin)
+            .add_plugin(TradePlugin)
+            .add_plugin(RoadsPlugin)
+            .add_plugin(WaterCyclePlugin)
+            .add_plugin(FloodingPlugin)
             .add_plugin(MaterialPlugin::<TileMaterial>::default());
         #[cfg(not(target_family = "wasm"))]
         app.add_plugin(self::editor_ui::EditorUiPlugin);
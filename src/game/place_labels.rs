@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::AppLooplessStateExt;
+
+use crate::ui::*;
+
+use super::{board::Board, game_state::GameState, level::Level};
+
+pub struct PlaceLabelsPlugin;
+
+impl Plugin for PlaceLabelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::InGame, spawn_labels)
+            .add_exit_system(GameState::InGame, despawn_labels);
+    }
+}
+
+/// The root of a place's label - each entry into the game spawns them afresh, so leaving it
+/// takes them away again.
+#[derive(Component)]
+struct PlaceLabel;
+
+/// Labels each of the level's places on the board, following its tile as the camera moves.
+fn spawn_labels(
+    mut commands: Commands,
+    level: Res<Level>,
+    boards: Query<&Board>,
+    cameras: Query<Entity, With<Camera>>,
+) {
+    let (board, camera) = match (boards.get_single(), cameras.get_single()) {
+        (Ok(board), Ok(camera)) => (board, camera),
+        _ => return,
+    };
+    for place in level.places.iter() {
+        if let Some(tile) = board.children.get(&place.position) {
+            let label = commands
+                .ui_root()
+                .world_position(*tile, camera)
+                .padding(0.)
+                .for_state(GameState::InGame)
+                .with_children(|parent| {
+                    parent.div().opaque().padding(2.).with_children(|parent| {
+                        parent.text(&place.name).size(15.);
+                    });
+                });
+            if let Some(mut label) = label {
+                label.insert(PlaceLabel);
+            }
+        }
+    }
+}
+
+fn despawn_labels(mut commands: Commands, labels: Query<Entity, With<PlaceLabel>>) {
+    for label in labels.iter() {
+        commands.entity(label).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+    use iyes_loopless::prelude::*;
+
+    use super::{PlaceLabel, PlaceLabelsPlugin};
+    use crate::game::{
+        board::Board,
+        game_state::GameState,
+        level::{Level, TileInfo},
+    };
+
+    #[test]
+    fn retrying_leaves_one_label_per_place() {
+        let level: Level = serde_json::from_value(serde_json::json!({
+            "tiles": [[TileInfo::default()]],
+            "title": null,
+            "initial_description": null,
+            "sidebar_text": null,
+            "width": 1,
+            "height": 1,
+            "places": [{ "name": "Worsley", "position": [0, 0] }]
+        }))
+        .unwrap();
+        let mut app = App::new();
+        app.add_loopless_state(GameState::Setup)
+            .add_plugin(PlaceLabelsPlugin)
+            .insert_resource(level);
+        let tile = app.world.spawn_empty().id();
+        let mut board = Board::default();
+        board.children.insert((0, 0), tile);
+        app.world.spawn(board);
+        app.world.spawn(Camera::default());
+
+        let mut count_labels = |state: GameState| {
+            app.insert_resource(NextState(state));
+            app.update();
+            app.world
+                .query_filtered::<(), With<PlaceLabel>>()
+                .iter(&app.world)
+                .count()
+        };
+        assert_eq!(count_labels(GameState::InGame), 1);
+        assert_eq!(count_labels(GameState::Failed), 0);
+        assert_eq!(count_labels(GameState::Setup), 0);
+        assert_eq!(count_labels(GameState::InGame), 1);
+    }
+}
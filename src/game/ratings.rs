@@ -0,0 +1,72 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+/// The most stars a level can award.
+pub const MAX_STARS: usize = 3;
+
+/// The level file being played, so its rating can be kept - `None` for levels that don't come
+/// from a file, like random ones.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CurrentLevelFile(pub Option<String>);
+
+/// The best star rating earned on each level file.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BestRatings(pub HashMap<String, usize>);
+
+impl BestRatings {
+    #[cfg(not(target_family = "wasm"))]
+    fn path() -> std::path::PathBuf {
+        let mut path = bevy::asset::FileAssetIo::get_base_path();
+        path.push("best_ratings.json");
+        path
+    }
+
+    /// The ratings saved by earlier games - there's nowhere to keep them on the web, so they
+    /// only last as long as the page there.
+    pub fn load() -> Self {
+        #[cfg(not(target_family = "wasm"))]
+        if let Ok(file) = std::fs::read(Self::path()) {
+            return serde_json::from_slice(&file).unwrap_or_default();
+        }
+        Self::default()
+    }
+
+    /// Keeps the rating if it beats the level's best so far.
+    pub fn record(&mut self, file: &str, stars: usize) {
+        let best = self.0.entry(file.to_string()).or_default();
+        if stars > *best {
+            *best = stars;
+            #[cfg(not(target_family = "wasm"))]
+            if let Ok(json) = serde_json::to_string_pretty(self) {
+                if let Err(e) = std::fs::write(Self::path(), json) {
+                    error!("Couldn't save ratings - {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Three stars for coming in at par or under, two for within half as much again, and one for
+/// finishing at all.
+pub fn stars(cost: usize, par: usize) -> usize {
+    if cost <= par {
+        3
+    } else if cost * 2 <= par * 3 {
+        2
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::stars;
+
+    #[test]
+    fn stars_fall_away_over_par() {
+        assert_eq!(stars(90, 100), 3);
+        assert_eq!(stars(100, 100), 3);
+        assert_eq!(stars(150, 100), 2);
+        assert_eq!(stars(151, 100), 1);
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::IntoConditionalSystem;
+
+use super::{board::*, game_state::GameState, level::Level};
+
+pub struct RoadsPlugin;
+
+impl Plugin for RoadsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeveredRoads>().add_system(
+            check_roads
+                .run_in_state(GameState::InGame)
+                .label("check_roads"),
+        );
+    }
+}
+
+/// The names of any roads the level needs kept open that no longer join up.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct SeveredRoads(pub Vec<String>);
+
+/// Whether there's an unbroken road - including bridges - between two tiles.
+pub fn roads_connect<'a, T: IntoIterator<Item = &'a Tile>>(
+    width: usize,
+    height: usize,
+    topology: GridTopology,
+    tiles: T,
+    from: (usize, usize),
+    to: (usize, usize),
+) -> bool {
+    let mut road = vec![false; width * height];
+    for tile in tiles {
+        if tile.x < width && tile.y < height && tile.carries_road() {
+            road[tile.y * width + tile.x] = true;
+        }
+    }
+    let is_road = |(x, y): (usize, usize)| x < width && y < height && road[y * width + x];
+    if !is_road(from) || !is_road(to) {
+        return false;
+    }
+
+    let mut visited = vec![false; width * height];
+    visited[from.1 * width + from.0] = true;
+    let mut queue = VecDeque::from([from]);
+    while let Some(position) = queue.pop_front() {
+        if position == to {
+            return true;
+        }
+        for next in topology
+            .neighbours(position.0, position.1, width, height)
+            .into_iter()
+            .flatten()
+        {
+            let index = next.1 * width + next.0;
+            if is_road(next) && !visited[index] {
+                visited[index] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    false
+}
+
+fn check_roads(
+    level: Res<Level>,
+    tiles: Query<&Tile>,
+    changed: Query<(), Changed<Tile>>,
+    mut severed: ResMut<SeveredRoads>,
+) {
+    if changed.is_empty() && !level.is_changed() {
+        return;
+    }
+    let cut = level
+        .roads
+        .iter()
+        .filter(|road| {
+            !roads_connect(
+                level.width,
+                level.height,
+                level.topology,
+                tiles.iter(),
+                road.from,
+                road.to,
+            )
+        })
+        .map(|road| road.name.clone())
+        .collect::<Vec<_>>();
+    if severed.0 != cut {
+        severed.0 = cut;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::roads_connect;
+    use crate::game::board::{GridTopology, Tile, TileContents};
+
+    #[test]
+    fn bridges_keep_roads_connected() {
+        let mut tiles = (0..4)
+            .map(|x| Tile {
+                x,
+                contents: TileContents::Road,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        assert!(roads_connect(
+            4,
+            1,
+            GridTopology::Square,
+            tiles.iter(),
+            (0, 0),
+            (3, 0)
+        ));
+
+        tiles[2].contents = TileContents::Bridge;
+        assert!(roads_connect(
+            4,
+            1,
+            GridTopology::Square,
+            tiles.iter(),
+            (0, 0),
+            (3, 0)
+        ));
+
+        tiles[2].contents = TileContents::Lock;
+        assert!(!roads_connect(
+            4,
+            1,
+            GridTopology::Square,
+            tiles.iter(),
+            (0, 0),
+            (3, 0)
+        ));
+    }
+}
@@ -0,0 +1,499 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet, VecDeque},
+};
+
+use super::{
+    board::{GridTopology, Tile, TileContents, Topology},
+    level::{Level, LevelTools},
+    simulation::{can_hold_water, joins, receives_from, WaterNetwork, WaterSolution},
+};
+
+/// Something built on a tile to carry water on to the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub x: usize,
+    pub y: usize,
+    pub contents: TileContents,
+    pub cost: usize,
+}
+
+/// The cheapest way found to carry water from one of a set of tiles to another tile - or to
+/// several, branching on the way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub cost: usize,
+    /// The tile the water is carried on from.
+    pub from: (usize, usize),
+    pub placements: Vec<Placement>,
+}
+
+/// A level's tiles laid out for finding routes across, going by the same rules as building
+/// canals, locks and aquaducts in the game.
+#[derive(Debug, Clone)]
+pub struct RouteMap {
+    width: usize,
+    height: usize,
+    topology: GridTopology,
+    tools: LevelTools,
+    tiles: Vec<Tile>,
+}
+
+/// How the cheapest way from a tile to a set of goals was found, while searching for a tree.
+#[derive(Debug, Clone, Copy)]
+enum Branch {
+    Unreached,
+    /// The tile is the only goal in the set.
+    Goal,
+    /// The goals are split into two sets, each reached from the tile on its own.
+    Split(usize),
+    /// The water carries on to the next tile, which reaches every goal in the set.
+    Next(usize),
+}
+
+/// A tile as it would be along a route - built on or not - and how it was reached.
+struct Step {
+    tile: Tile,
+    cost: usize,
+    built: Option<usize>,
+    previous: Option<usize>,
+}
+
+impl RouteMap {
+    pub fn new(level: &Level) -> Self {
+        let mut tiles = Vec::with_capacity(level.width * level.height);
+        for y in 0..level.height {
+            for x in 0..level.width {
+                tiles.push(level.tiles[x][y].to_tile(x, y));
+            }
+        }
+        Self {
+            width: level.width,
+            height: level.height,
+            topology: level.topology,
+            tools: level.tools.clone(),
+            tiles,
+        }
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> Option<&Tile> {
+        if x < self.width && y < self.height {
+            self.tiles.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn tile_mut(&mut self, x: usize, y: usize) -> Option<&mut Tile> {
+        if x < self.width && y < self.height {
+            self.tiles.get_mut(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn tools_mut(&mut self) -> &mut LevelTools {
+        &mut self.tools
+    }
+
+    /// Every tile that holds water from the start.
+    pub fn sources(&self) -> Vec<(usize, usize)> {
+        self.tiles
+            .iter()
+            .filter(|tile| tile.is_water_source())
+            .map(|tile| (tile.x, tile.y))
+            .collect()
+    }
+
+    /// The body of water the tile at `from` is part of - every tile joined to it that the
+    /// sources' water flows into, the way the game settles it.
+    pub fn body(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut wet = vec![false; self.tiles.len()];
+        let mut queue = VecDeque::new();
+        for tile in self.tiles.iter().filter(|tile| tile.is_water_source()) {
+            wet[tile.y * self.width + tile.x] = true;
+            queue.push_back(tile);
+        }
+        while let Some(tile) = queue.pop_front() {
+            for neighbour in self.neighbours(tile) {
+                let index = neighbour.y * self.width + neighbour.x;
+                if !wet[index] && receives_from(neighbour, tile) {
+                    wet[index] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        let mut body = vec![from];
+        let mut queue = VecDeque::from([from]);
+        while let Some((x, y)) = queue.pop_front() {
+            let tile = &self.tiles[y * self.width + x];
+            for neighbour in self.neighbours(tile) {
+                let position = (neighbour.x, neighbour.y);
+                if wet[neighbour.y * self.width + neighbour.x]
+                    && joins(tile, neighbour)
+                    && !body.contains(&position)
+                {
+                    body.push(position);
+                    queue.push_back(position);
+                }
+            }
+        }
+        body
+    }
+
+    fn neighbours<'a>(&'a self, tile: &Tile) -> impl Iterator<Item = &'a Tile> {
+        self.topology
+            .neighbours(tile.x, tile.y, self.width, self.height)
+            .into_iter()
+            .flatten()
+            .map(|(x, y)| &self.tiles[y * self.width + x])
+    }
+
+    /// Settles the water across the map, the way the game would.
+    pub fn water(&self) -> WaterSolution {
+        WaterNetwork::new(self.width, self.height, self.tiles.iter())
+            .with_topology(self.topology)
+            .solve()
+    }
+
+    /// Builds a route's placements into the map, so later routes can carry on from them.
+    pub fn build(&mut self, placements: &[Placement]) {
+        for placement in placements {
+            let index = placement.y * self.width + placement.x;
+            self.tiles[index].contents = placement.contents;
+            self.tiles[index].water_surface = None;
+        }
+    }
+
+    /// What could be built on a tile to carry water on from a neighbour at the given level, and
+    /// what each would cost. Tiles that can already hold water are used as they are.
+    fn options(&self, tile: &Tile, level: usize) -> Vec<(Tile, Option<usize>)> {
+        if can_hold_water(tile) {
+            return vec![(tile.clone(), None)];
+        }
+        // Building replaces whatever water surface the tile had with the new contents' own
+        let with = |contents| Tile {
+            contents,
+            water_surface: None,
+            ..tile.clone()
+        };
+        let mut options = vec![];
+        if self.tools.canal {
+            let canal = if tile.contents == TileContents::Road {
+                TileContents::Bridge
+            } else {
+                TileContents::Canal
+            };
+            options.push((with(canal), tile.get_canal_cost()));
+        }
+        // Locks and aquaducts would cut a road, rather than bridge it
+        if tile.contents == TileContents::None {
+            if self.tools.lock {
+                options.push((with(TileContents::Lock), tile.get_lock_cost()));
+            }
+            if self.tools.aquaduct && level > tile.z {
+                options.push((
+                    with(TileContents::Aquaduct(level - tile.z)),
+                    tile.get_aquaduct_cost(),
+                ));
+            }
+        }
+        options
+            .into_iter()
+            .filter_map(|(tile, cost)| Some((tile, Some(cost?))))
+            .collect()
+    }
+
+    /// The cheapest route carrying water from any of the `from` tiles to the tile at `to`,
+    /// or `None` if there's no way to get it there. Routes only go the way water flows, so they
+    /// step down through locks but never climb them.
+    pub fn cheapest_route(&self, from: &[(usize, usize)], to: (usize, usize)) -> Option<Route> {
+        let mut steps = vec![];
+        let mut queue = BinaryHeap::new();
+        for (x, y) in from.iter() {
+            if let Some(tile) = self.tile(*x, *y).filter(|tile| can_hold_water(tile)) {
+                queue.push(Reverse((0, steps.len())));
+                steps.push(Step {
+                    tile: tile.clone(),
+                    cost: 0,
+                    built: None,
+                    previous: None,
+                });
+            }
+        }
+
+        let mut settled = HashSet::new();
+        while let Some(Reverse((cost, index))) = queue.pop() {
+            let tile = &steps[index].tile;
+            let key = (tile.x, tile.y, tile.water_level(), tile.contents);
+            if !settled.insert(key) {
+                continue;
+            }
+
+            if (tile.x, tile.y) == to {
+                return Some(Self::route_to(&steps, index));
+            }
+
+            let tile = tile.clone();
+            for (x, y) in self
+                .topology
+                .neighbours(tile.x, tile.y, self.width, self.height)
+                .into_iter()
+                .flatten()
+            {
+                let neighbour = &self.tiles[y * self.width + x];
+                for (next, built) in self.options(neighbour, tile.water_level()) {
+                    let key = (x, y, next.water_level(), next.contents);
+                    if !carries_on(&tile, &next) || settled.contains(&key) {
+                        continue;
+                    }
+                    let cost = cost + built.unwrap_or_default();
+                    queue.push(Reverse((cost, steps.len())));
+                    steps.push(Step {
+                        tile: next,
+                        cost,
+                        built,
+                        previous: Some(index),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// The cheapest network of routes carrying water from any one of the `from` tiles to every
+    /// tile in `to`, sharing the stretches they have in common - or `None` if there's no way to
+    /// get the water to all of them, or the cheapest network would need a tile built two ways.
+    ///
+    /// This searches every way the goals could branch off one another, which gets much slower
+    /// with each goal, so it's only meant for a handful of them at a time.
+    pub fn cheapest_tree(&self, from: &[(usize, usize)], to: &[(usize, usize)]) -> Option<Route> {
+        let mut goals = to.to_vec();
+        goals.sort_unstable();
+        goals.dedup();
+        if goals.is_empty() {
+            return None;
+        }
+
+        // Aquaducts carry the water at the level of the tile feeding them, so those are the
+        // only levels worth building them to
+        let mut levels = self
+            .tiles
+            .iter()
+            .map(|tile| tile.water_level())
+            .collect::<Vec<_>>();
+        levels.sort_unstable();
+        levels.dedup();
+        let mut states: Vec<(Tile, Option<usize>)> = vec![];
+        let mut at = vec![vec![]; self.tiles.len()];
+        for (index, tile) in self.tiles.iter().enumerate() {
+            let mut options = self.options(tile, 0);
+            for level in levels.iter().filter(|level| **level > tile.z) {
+                options.extend(
+                    self.options(tile, *level)
+                        .into_iter()
+                        .filter(|(option, _)| matches!(option.contents, TileContents::Aquaduct(_))),
+                );
+            }
+            for option in options {
+                at[index].push(states.len());
+                states.push(option);
+            }
+        }
+        let weight = |state: usize| states[state].1.unwrap_or_default();
+
+        // costs[goals][state] is the cheapest way for water on the state's tile to reach every
+        // goal in the set, counting whatever is built on the tile itself
+        let sets = 1 << goals.len();
+        let mut costs = vec![vec![usize::MAX; states.len()]; sets];
+        let mut branches = vec![vec![Branch::Unreached; states.len()]; sets];
+        for set in 1..sets {
+            if set.count_ones() == 1 {
+                let (x, y) = goals[set.trailing_zeros() as usize];
+                if x < self.width && y < self.height {
+                    for state in at[y * self.width + x].iter() {
+                        costs[set][*state] = weight(*state);
+                        branches[set][*state] = Branch::Goal;
+                    }
+                }
+            } else {
+                for state in 0..states.len() {
+                    let mut part = (set - 1) & set;
+                    while part > 0 {
+                        let (a, b) = (costs[part][state], costs[set ^ part][state]);
+                        if a != usize::MAX && b != usize::MAX {
+                            let cost = a + b - weight(state);
+                            if cost < costs[set][state] {
+                                costs[set][state] = cost;
+                                branches[set][state] = Branch::Split(part);
+                            }
+                        }
+                        part = (part - 1) & set;
+                    }
+                }
+            }
+
+            // Work back from the tiles that reach these goals to the tiles that could feed them
+            let mut queue = (0..states.len())
+                .filter(|state| costs[set][*state] != usize::MAX)
+                .map(|state| Reverse((costs[set][state], state)))
+                .collect::<BinaryHeap<_>>();
+            while let Some(Reverse((cost, state))) = queue.pop() {
+                if cost > costs[set][state] {
+                    continue;
+                }
+                let tile = &states[state].0;
+                for neighbour in self.neighbours(tile) {
+                    for feeding in at[neighbour.y * self.width + neighbour.x].iter() {
+                        let cost = cost + weight(*feeding);
+                        if cost < costs[set][*feeding] && carries_on(&states[*feeding].0, tile) {
+                            costs[set][*feeding] = cost;
+                            branches[set][*feeding] = Branch::Next(state);
+                            queue.push(Reverse((cost, *feeding)));
+                        }
+                    }
+                }
+            }
+        }
+
+        let all = sets - 1;
+        let root = from
+            .iter()
+            .filter_map(|(x, y)| self.tile(*x, *y))
+            .filter(|tile| can_hold_water(tile))
+            .flat_map(|tile| at[tile.y * self.width + tile.x].iter().copied())
+            .min_by_key(|state| costs[all][*state])
+            .filter(|state| costs[all][*state] != usize::MAX)?;
+
+        let mut placements: Vec<Placement> = vec![];
+        let mut stack = vec![(all, root)];
+        while let Some((set, state)) = stack.pop() {
+            let (tile, built) = &states[state];
+            if let Some(cost) = built {
+                match placements.iter().find(|p| (p.x, p.y) == (tile.x, tile.y)) {
+                    Some(placement) if placement.contents != tile.contents => return None,
+                    Some(_) => {}
+                    None => placements.push(Placement {
+                        x: tile.x,
+                        y: tile.y,
+                        contents: tile.contents,
+                        cost: *cost,
+                    }),
+                }
+            }
+            match branches[set][state] {
+                Branch::Split(part) => {
+                    stack.push((set ^ part, state));
+                    stack.push((part, state));
+                }
+                Branch::Next(next) => stack.push((set, next)),
+                Branch::Goal | Branch::Unreached => {}
+            }
+        }
+        let tile = &states[root].0;
+        Some(Route {
+            cost: placements.iter().map(|p| p.cost).sum(),
+            from: (tile.x, tile.y),
+            placements,
+        })
+    }
+
+    fn route_to(steps: &[Step], mut index: usize) -> Route {
+        let cost = steps[index].cost;
+        let mut placements = vec![];
+        loop {
+            let step = &steps[index];
+            if let Some(cost) = step.built {
+                placements.push(Placement {
+                    x: step.tile.x,
+                    y: step.tile.y,
+                    contents: step.tile.contents,
+                    cost,
+                });
+            }
+            match step.previous {
+                Some(previous) => index = previous,
+                None => break,
+            }
+        }
+        placements.reverse();
+        Route {
+            cost,
+            from: (steps[index].tile.x, steps[index].tile.y),
+            placements,
+        }
+    }
+}
+
+/// Whether the water on `tile` carries on to `next` - flowing into it the way the game settles
+/// water, or meeting a source that's already wet and joining up with it.
+fn carries_on(tile: &Tile, next: &Tile) -> bool {
+    receives_from(next, tile) || (next.is_water_source() && joins(tile, next))
+}
+
+#[cfg(test)]
+mod test {
+    use super::RouteMap;
+    use crate::game::{
+        board::{TileContents, TileCostModifier},
+        level::{Level, TileInfo},
+    };
+
+    fn strip(heights: &[usize]) -> Level {
+        serde_json::from_value(serde_json::json!({
+            "tiles": heights
+                .iter()
+                .map(|height| vec![TileInfo { height: *height, ..Default::default() }])
+                .collect::<Vec<_>>(),
+            "title": null,
+            "initial_description": null,
+            "sidebar_text": null,
+            "width": heights.len(),
+            "height": 1
+        }))
+        .unwrap()
+    }
+
+    fn built(level: &Level, to: (usize, usize)) -> Option<(usize, Vec<TileContents>)> {
+        let map = RouteMap::new(level);
+        let route = map.cheapest_route(&map.sources(), to)?;
+        let contents = route.placements.iter().map(|p| p.contents).collect();
+        Some((route.cost, contents))
+    }
+
+    #[test]
+    fn routes_step_down_with_locks_and_cross_valleys_on_aquaducts() {
+        let mut level = strip(&[2, 1]);
+        level.tiles[0][0].contents = TileContents::River;
+        assert_eq!(built(&level, (1, 0)), Some((4, vec![TileContents::Lock])));
+
+        // Water never climbs into a lock, so there's no way up from a lower river
+        let mut level = strip(&[1, 2]);
+        level.tiles[0][0].contents = TileContents::River;
+        assert_eq!(built(&level, (1, 0)), None);
+
+        // Too deep for a lock to reach the bottom
+        let mut level = strip(&[5, 5, 0, 5]);
+        level.tiles[0][0].contents = TileContents::River;
+        assert_eq!(
+            built(&level, (3, 0)),
+            Some((
+                3 + 5 + 3,
+                vec![
+                    TileContents::Canal,
+                    TileContents::Aquaduct(5),
+                    TileContents::Canal
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn blocked_tiles_have_no_route() {
+        let mut level = strip(&[0, 0, 0]);
+        level.tiles[0][0].contents = TileContents::River;
+        level.tiles[1][0].cost_modifier = TileCostModifier::Blocked;
+        assert_eq!(built(&level, (2, 0)), None);
+    }
+}
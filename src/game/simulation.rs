@@ -1,356 +1,346 @@
-use bevy::{prelude::*, utils::HashMap};
+mod water_graph;
+mod water_network;
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
 use iyes_loopless::{prelude::IntoConditionalSystem, state::NextState};
 
 use super::{
     board::*,
-    game_state::{GameActionMode, GameActions, GameState},
+    game_state::{GameActionMode, GameActions, GameResources, GameState},
     in_game_ui::SidebarText,
-    initial_description::CurrentDescription,
-    level::{EventAction, Level, LevelEvent, LevelEventType, LevelTools, PendingLevelEvents},
+    initial_description::{ChoiceMade, CurrentDescription},
+    level::{
+        EventAction, Level, LevelEvent, LevelEventType, LevelFlags, LevelTools, PendingLevelEvents,
+    },
+    roads::SeveredRoads,
+    water_cycle::WaterLevels,
 };
 
+pub use water_graph::*;
+pub use water_network::*;
+
 pub struct SimulationPlugin;
 
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<LevelEvent>()
             .init_resource::<PendingLevelEvents>()
+            .init_resource::<LevelFlags>()
+            .init_resource::<PlayTime>()
             .init_resource::<ActionTracker>()
             .init_resource::<LevelTools>()
+            .init_resource::<WaterShortages>()
+            .init_resource::<WaterGraph>()
+            .init_resource::<WaterSimulationRuns>()
             .add_system(setup_level_events.run_in_state(GameState::InGame))
-            .add_system(run_water_simulation.run_in_state(GameState::InGame))
-            .add_system(propogate_water_sources.run_in_state(GameState::InGame))
+            .add_system(
+                run_water_simulation
+                    .run_in_state(GameState::InGame)
+                    .label("water_simulation"),
+            )
             .add_system(track_actions.run_in_state(GameState::InGame))
             .add_system(
                 check_goals_for_sucess
                     .run_in_state(GameState::InGame)
-                    .label("check_goal"),
+                    .label("check_goal")
+                    .after("water_simulation")
+                    .after("check_roads"),
             )
             .add_system(
                 process_level_event
                     .run_in_state(GameState::InGame)
-                    .after("check_gaol"),
+                    .after("check_goal"),
+            )
+            .add_system(advance_play_time.run_in_state(GameState::InGame))
+            .add_system(
+                check_level_triggers
+                    .run_in_state(GameState::InGame)
+                    .after("water_simulation"),
+            )
+            .add_system(
+                check_for_failure
+                    .run_in_state(GameState::InGame)
+                    .after("check_goal"),
             );
     }
 }
 
-#[derive(Default)]
-struct OnlyDry(bool);
-
-fn run_water_simulation(
-    mut commands: Commands,
-    tiles: Query<(Entity, &Tile, &TileNeighbours)>,
-    mut only_dry: Local<OnlyDry>,
-) {
-    only_dry.0 = !only_dry.0;
-    for (entity, tile, neighbours) in tiles.iter() {
-        if tile.wetness == Wetness::WaterSource {
-            continue;
-        }
-
-        let neighbours = neighbours
-            .0
-            .iter()
-            .map(|e| {
-                if let Some(e) = e {
-                    tiles.get(*e).ok()
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-        if tile.contents == TileContents::Canal {
-            let z = tile.z;
-            let neighbours = check_neighbours(
-                &neighbours,
-                |neighbour| {
-                    let (nz, diff) = match neighbour.contents {
-                        TileContents::Aquaduct(h) => (h + neighbour.z, 1),
-                        _ => (neighbour.z, 1),
-                    };
-                    neighbour.wetness != Wetness::Dry && z <= nz && z.abs_diff(nz) < diff
-                },
-                |t, _, _e| t.wetness,
-            );
-
-            propogate_wetness(neighbours, tile, &mut commands, entity, only_dry.0);
-        } else if tile.contents == TileContents::Lock {
-            let z = tile.z;
-            let neighbours = check_neighbours(
-                &neighbours,
-                |neighbour| {
-                    let (nz, diff) = match neighbour.contents {
-                        TileContents::Aquaduct(h) => (h + neighbour.z, 1),
-                        _ => (neighbour.z, 5),
-                    };
-                    neighbour.wetness != Wetness::Dry && z <= nz && z.abs_diff(nz) < diff
-                },
-                |t, _, _e| t.wetness,
-            );
+/// The parts of the network that have run dry because their locks use more water than they get.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct WaterShortages(pub Vec<WaterShortage>);
 
-            propogate_wetness(neighbours, tile, &mut commands, entity, only_dry.0);
-        } else if let TileContents::Aquaduct(h) = tile.contents {
-            let z = tile.z + h;
-            let neighbours = check_neighbours(
-                &neighbours,
-                |neighbour| {
-                    let nz = match neighbour.contents {
-                        TileContents::Aquaduct(h) => h + neighbour.z,
-                        _ => neighbour.z,
-                    };
-                    neighbour.wetness != Wetness::Dry && z == nz
-                },
-                |t, _, _e| t.wetness,
-            );
-
-            propogate_wetness(neighbours, tile, &mut commands, entity, only_dry.0);
-        }
+impl WaterShortages {
+    pub fn at(&self, x: usize, y: usize) -> Option<&WaterShortage> {
+        self.0.iter().find(|s| s.tiles.contains(&(x, y)))
     }
 }
 
-fn propogate_wetness(
-    neighbours: [NeighbourMatch<(TileContents, usize, Wetness)>; 4],
-    tile: &Tile,
-    commands: &mut Commands,
-    entity: Entity,
-    only_dry: bool,
-) {
-    let min_wetness = neighbours.iter().fold(Wetness::Dry, |wetness_val, n| {
-        if let NeighbourMatch::Matches((_, _, wetness)) = n {
-            match wetness {
-                Wetness::Dry => wetness_val,
-                Wetness::WaterSource => *wetness,
-                Wetness::Wet(a) => match wetness_val {
-                    Wetness::Dry => *wetness,
-                    Wetness::WaterSource => wetness_val,
-                    Wetness::Wet(b) => Wetness::Wet(*a.min(&b)),
-                },
-            }
-        } else {
-            wetness_val
-        }
-    });
+/// How many times the water has been settled - it's left alone while the board sits idle - and
+/// how many tiles the last of those runs read.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaterSimulationRuns {
+    pub runs: usize,
+    pub tiles: usize,
+}
 
-    let (should_update, new_wetness) = match (tile.wetness, min_wetness) {
-        (Wetness::Dry, Wetness::WaterSource) => (true, Wetness::Wet(1)),
-        (Wetness::Dry, Wetness::Wet(a)) => (true, Wetness::Wet(a + 1)),
-        (Wetness::Wet(_), Wetness::Dry) => (true, Wetness::Dry),
-        (Wetness::Wet(a), Wetness::WaterSource) => {
-            if a != 1 {
-                (true, Wetness::Wet(1))
-            } else {
-                (false, Wetness::Wet(a))
-            }
-        }
-        (Wetness::Wet(a), Wetness::Wet(b)) => {
-            if a != b + 1 {
-                (true, Wetness::Dry)
-            } else {
-                (false, Wetness::Dry)
-            }
-        }
-        _ => (false, Wetness::Dry),
+/// Settles the water around any tiles that changed since the last run - or across the whole
+/// board if the sea or rivers moved, or the board was just laid out. Only the tiles around the
+/// changes are read, and nothing is recalculated while the board sits idle.
+pub(super) fn run_water_simulation(
+    mut tiles: Query<(&mut Tile, &mut WetnessSource)>,
+    board: Query<(&Board, ChangeTrackers<Board>)>,
+    mut shortages: ResMut<WaterShortages>,
+    mut graph: ResMut<WaterGraph>,
+    levels: Res<WaterLevels>,
+    mut runs: ResMut<WaterSimulationRuns>,
+) {
+    let (board, board_changes) = match board.get_single() {
+        Ok(board) => board,
+        Err(_) => return,
     };
-
-    if only_dry && new_wetness != Wetness::Dry {
+    let changed = tiles
+        .iter_mut()
+        .filter(|(tile, _)| tile.is_changed())
+        .map(|(tile, _)| (tile.x, tile.y))
+        .collect::<Vec<_>>();
+    let everything = levels.is_changed() || board_changes.is_changed();
+    if changed.is_empty() && !everything {
         return;
     }
 
-    if should_update {
-        info!(
-            "Updating tile {}, {} wetness from {:?} to {new_wetness:?} {min_wetness:?}",
-            tile.x, tile.y, tile.wetness
-        );
-        let mut tile = tile.clone();
-        tile.wetness = new_wetness;
+    let (network, region) = if everything {
+        let network = WaterNetwork::new(
+            board.width,
+            board.height,
+            tiles.iter().map(|(tile, _)| tile),
+        )
+        .with_topology(board.topology);
+        let region = network.tiles().map(|tile| (tile.x, tile.y)).collect();
+        (network, region)
+    } else {
+        WaterNetwork::around(
+            board.width,
+            board.height,
+            board.topology,
+            changed,
+            |x, y| {
+                board
+                    .children
+                    .get(&(x, y))
+                    .and_then(|entity| tiles.get(*entity).ok())
+                    .map(|(tile, _)| tile)
+            },
+        )
+    };
+    let network = network.with_levels(levels.tide, levels.river);
+    *runs = WaterSimulationRuns {
+        runs: runs.runs + 1,
+        tiles: network.tiles().count(),
+    };
+    let solution = network.solve_region(&region);
+    let region_set = region.iter().copied().collect::<HashSet<_>>();
 
-        let mut cmd = commands.entity(entity);
-        cmd.insert(tile);
+    // Shortages never cross the edge of the region, so the ones outside it still stand
+    let updated = shortages
+        .0
+        .iter()
+        .filter(|shortage| {
+            shortage
+                .tiles
+                .first()
+                .map(|position| !region_set.contains(position))
+                .unwrap_or(true)
+        })
+        .chain(solution.shortages().iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    if shortages.0 != updated {
+        shortages.0 = updated;
+    }
+    // Bodies of water never cross the edge of the region either
+    if everything {
+        *graph = solution.graph().clone();
+    } else {
+        graph.replace_region(&region_set, solution.graph());
+    }
+    let region_tiles = region
+        .iter()
+        .filter_map(|position| board.children.get(position))
+        .filter_map(|entity| tiles.get(*entity).ok());
+    if solution.is_settled(region_tiles) {
+        return;
     }
-}
-
-fn propogate_water_sources(
-    mut commands: Commands,
-    tiles: Query<(Entity, &Tile, (&TileNeighbours, &WetnessSource))>,
-) {
-    let mut changes = HashMap::new();
-    for (entity, tile, (neighbours, source)) in tiles.iter() {
-        let neighbours = neighbours
-            .0
-            .iter()
-            .map(|e| {
-                if let Some(e) = e {
-                    tiles.get(*e).ok()
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-
-        let neighbours = if tile.wetness == Wetness::Dry {
-            None
-        } else if tile.wetness == Wetness::WaterSource {
-            let z = tile.z;
-            let neighbours = check_neighbours(
-                &neighbours,
-                |neighbour| {
-                    let (nz, diff) = match neighbour.contents {
-                        TileContents::River => (neighbour.z, 15),
-                        TileContents::Aquaduct(h) => (h + neighbour.z, 1),
-                        _ => (neighbour.z, 1),
-                    };
-                    neighbour.wetness != Wetness::Dry && z.abs_diff(nz) < diff
-                },
-                |t, (_, n_source), e| (**n_source, *e, t.x, t.y),
-            );
-            Some(neighbours)
-        } else if tile.contents == TileContents::Canal {
-            let z = tile.z;
-            let neighbours = check_neighbours(
-                &neighbours,
-                |neighbour| {
-                    let (nz, diff) = match neighbour.contents {
-                        TileContents::Aquaduct(h) => (h + neighbour.z, 1),
-                        _ => (neighbour.z, 1),
-                    };
-                    neighbour.wetness != Wetness::Dry && z.abs_diff(nz) < diff
-                },
-                |t, (_, n_source), e| (**n_source, *e, t.x, t.y),
-            );
-
-            Some(neighbours)
-        } else if tile.contents == TileContents::Lock {
-            let z = tile.z;
-            let neighbours = check_neighbours(
-                &neighbours,
-                |neighbour| {
-                    let (nz, diff) = match neighbour.contents {
-                        TileContents::Aquaduct(h) => (h + neighbour.z, 1),
-                        _ => (neighbour.z, 5),
-                    };
-                    neighbour.wetness != Wetness::Dry && z.abs_diff(nz) < diff
-                },
-                |t, (_, n_source), e| (**n_source, *e, t.x, t.y),
-            );
 
-            Some(neighbours)
-        } else if let TileContents::Aquaduct(h) = tile.contents {
-            let z = tile.z + h;
-            let neighbours = check_neighbours(
-                &neighbours,
-                |neighbour| {
-                    let nz = match neighbour.contents {
-                        TileContents::Aquaduct(h) => h + neighbour.z,
-                        _ => neighbour.z,
-                    };
-                    neighbour.wetness != Wetness::Dry && z == nz
-                },
-                |t, (_, n_source), e| (**n_source, *e, t.x, t.y),
-            );
-            Some(neighbours)
-        } else {
-            None
+    for position in region.iter() {
+        let state = match solution.get(position.0, position.1) {
+            Some(state) => state,
+            None => continue,
         };
-
-        if let Some(neighbours) = neighbours {
-            let mut min_source = *source;
-
-            for n in neighbours.iter() {
-                if let NeighbourMatch::Matches((_, _, (s, e, _x, _y))) = n {
-                    let s = if let Some(c) = changes.get(e) { *c } else { *s };
-                    min_source = min_source.min(s);
-                }
+        if let Some((mut tile, mut source)) = board
+            .children
+            .get(position)
+            .and_then(|entity| tiles.get_mut(*entity).ok())
+        {
+            if tile.wetness != state.wetness {
+                tile.wetness = state.wetness;
             }
-
-            if min_source != *source {
-                info!(
-                    "Updating Min Source for {:?},{:?} - from {source:?} to {min_source:?}",
-                    tile.x, tile.y
-                );
-                if let Some(value) = changes.get(&entity) {
-                    changes.insert(entity, min_source.min(*value));
-                } else {
-                    changes.insert(entity, min_source);
-                }
+            if tile.flooded != state.flooded {
+                tile.flooded = state.flooded;
             }
-            for n in neighbours.iter() {
-                if let NeighbourMatch::Matches((_, _, (source, e, x, y))) = n {
-                    if *source != min_source {
-                        info!("Updating Min Source for neighbour {:?},{:?} - from {source:?} to {min_source:?}", x,y);
-                        if let Some(value) = changes.get(e) {
-                            changes.insert(*e, min_source.min(*value));
-                        } else {
-                            changes.insert(*e, min_source);
-                        }
-                    }
-                }
+            if *source != state.source {
+                *source = state.source;
             }
         }
     }
-
-    for (entity, source) in changes.iter() {
-        info!("Setting {entity:?} to {source:?}");
-        commands.entity(*entity).insert(*source);
-    }
 }
 
 fn setup_level_events(
     level: Res<Level>,
     mut level_events: ResMut<PendingLevelEvents>,
+    mut resources: ResMut<GameResources>,
     mut commands: Commands,
 ) {
     if !level.is_changed() {
         return;
     }
-    level_events.0 = level.events.iter().cloned().collect();
+    *level_events = PendingLevelEvents::new(&level.events);
+    resources.budget = level.budget;
+    commands.insert_resource(LevelFlags::default());
+    commands.insert_resource(PlayTime::default());
     commands.insert_resource(level.tools.clone());
+    commands.insert_resource(level.water_cycle.clone());
     commands.insert_resource(SidebarText(level.sidebar_text.clone()));
 }
 
 fn check_goals_for_sucess(
-    mut tiles: Query<(&mut Tile, &WetnessSource)>,
+    mut tiles: Query<&mut Tile>,
     mut commands: Commands,
     mut level_events: ResMut<PendingLevelEvents>,
     mut events: EventWriter<LevelEvent>,
+    severed: Res<SeveredRoads>,
+    graph: Res<WaterGraph>,
 ) {
+    if !severed.0.is_empty() {
+        return;
+    }
     let mut found_goal = false;
-    let mut min_wetness_source = WetnessSource::None;
-    for (tile, source) in tiles.iter() {
+    let mut goal_water = None;
+    for tile in tiles.iter() {
         if tile.is_goal {
             found_goal = true;
-            if tile.wetness == Wetness::Dry || *source == WetnessSource::None {
-                return;
-            }
+            let component = match graph.component_at(tile.x, tile.y) {
+                Some(component) if !component.sources.is_empty() => component,
+                _ => return,
+            };
 
-            info!("Found a goal that can be complete! {min_wetness_source:?} {source:?}");
-            if min_wetness_source == WetnessSource::None {
-                min_wetness_source = *source;
-                info!("We have no wetness yet");
-            } else if min_wetness_source != *source {
-                return;
+            info!(
+                "Found a goal that can be complete! {:?} {:?}",
+                goal_water.map(|c: &WaterComponent| c.source()),
+                component.source()
+            );
+            match goal_water {
+                None => goal_water = Some(component),
+                Some(other) if other != component => return,
+                _ => {}
             }
         }
     }
     if found_goal {
-        for (mut tile, _) in tiles.iter_mut() {
+        for mut tile in tiles.iter_mut() {
             if tile.is_goal {
                 tile.is_goal = false;
             }
         }
-        let mut pop = false;
-        if let Some(event) = level_events.0.front() {
-            if event.0 == LevelEventType::GoalReached {
-                pop = true;
-            }
+        let fired = level_events.fire(|trigger| match trigger {
+            LevelEventType::GoalReached => Some(true),
+            _ => None,
+        });
+        // The level carries on whenever a goal event fires, repeating or not - it's up to that
+        // event to set more goals or complete the level
+        let carries_on = !fired.is_empty();
+        for event in fired {
+            info!("Goal Reached Event {event:?}");
+            events.send(event);
         }
-        if pop {
-            if let Some(event) = level_events.0.pop_front() {
-                info!("Goal Reached Event {event:?}");
-                events.send(event);
-                return;
-            }
+        if !carries_on {
+            commands.insert_resource(NextState(GameState::Complete));
+        }
+    }
+}
+
+/// How long the level has been played for, in seconds - not counting time spent reading, or the
+/// boats still running once it's complete.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct PlayTime(pub f32);
+
+fn advance_play_time(time: Res<Time>, mut play_time: ResMut<PlayTime>) {
+    play_time.0 += time.delta_seconds();
+}
+
+/// Fires the events waiting on the state of the board, the budget, the clock or the flags.
+fn check_level_triggers(
+    tiles: Query<&Tile>,
+    board: Query<&Board>,
+    resources: Res<GameResources>,
+    flags: Res<LevelFlags>,
+    play_time: Res<PlayTime>,
+    mut level_events: ResMut<PendingLevelEvents>,
+    mut events: EventWriter<LevelEvent>,
+) {
+    let board = match board.get_single() {
+        Ok(board) => board,
+        Err(_) => return,
+    };
+    let fired = level_events.fire(|trigger| match trigger {
+        LevelEventType::TileWet(x, y) => {
+            let tile = board
+                .children
+                .get(&(*x, *y))
+                .and_then(|entity| tiles.get(*entity).ok());
+            Some(matches!(tile, Some(tile) if tile.wetness != Wetness::Dry))
         }
-        commands.insert_resource(NextState(GameState::Complete));
+        LevelEventType::CostAbove(cost) => Some(resources.cost_so_far > *cost),
+        LevelEventType::TimeElapsed(seconds) => Some(play_time.0 >= *seconds as f32),
+        LevelEventType::FlagSet(flag) => Some(flags.0.contains(flag)),
+        _ => None,
+    });
+    for event in fired {
+        info!("Triggered Event {event:?}");
+        events.send(event);
+    }
+}
+
+/// Fails the level once it's gone over budget, or there isn't enough left to build anything
+/// that could reach the goals.
+fn check_for_failure(
+    tiles: Query<&Tile>,
+    resources: Res<GameResources>,
+    tools: Res<LevelTools>,
+    mut commands: Commands,
+) {
+    let remaining = match resources.remaining() {
+        Some(remaining) => remaining,
+        None => return,
+    };
+    if resources.over_budget() {
+        info!("Over budget - {resources:?}");
+        commands.insert_resource(NextState(GameState::Failed));
+        return;
+    }
+    // Goals cleared by `check_goals_for_sucess` this frame have been reached, and the level
+    // might be about to set new ones - so there's nothing to fail on until it does.
+    if !tiles.iter().any(|tile| tile.is_goal) {
+        return;
+    }
+    let affordable = tiles
+        .iter()
+        .filter_map(|tile| tools.cheapest_on(tile))
+        .any(|cost| cost <= remaining);
+    if !affordable {
+        info!("Nothing left that can be afforded - {resources:?}");
+        commands.insert_resource(NextState(GameState::Failed));
     }
 }
 
@@ -359,12 +349,22 @@ pub struct ActionTracker {
     pub canals: usize,
     pub locks: usize,
     pub aquaducts: usize,
+    pub reservoirs: usize,
+    pub pumping_stations: usize,
+    pub tunnels: usize,
+    pub cuttings: usize,
+    pub embankments: usize,
     pub demolished: usize,
     pub total: usize,
 
     pub canals_since_last_event: usize,
     pub locks_since_last_event: usize,
     pub aquaducts_since_last_event: usize,
+    pub reservoirs_since_last_event: usize,
+    pub pumping_stations_since_last_event: usize,
+    pub tunnels_since_last_event: usize,
+    pub cuttings_since_last_event: usize,
+    pub embankments_since_last_event: usize,
     pub demolished_since_last_event: usize,
     pub total_since_last_event: usize,
 }
@@ -389,6 +389,26 @@ fn track_actions(
                 action_tracker.aquaducts += 1;
                 action_tracker.aquaducts_since_last_event += 1;
             }
+            GameActions::BuildReservoir(_) => {
+                action_tracker.reservoirs += 1;
+                action_tracker.reservoirs_since_last_event += 1;
+            }
+            GameActions::BuildPumpingStation(_) => {
+                action_tracker.pumping_stations += 1;
+                action_tracker.pumping_stations_since_last_event += 1;
+            }
+            GameActions::BuildTunnel(_, _) => {
+                action_tracker.tunnels += 1;
+                action_tracker.tunnels_since_last_event += 1;
+            }
+            GameActions::DigCutting(_) => {
+                action_tracker.cuttings += 1;
+                action_tracker.cuttings_since_last_event += 1;
+            }
+            GameActions::BuildEmbankment(_) => {
+                action_tracker.embankments += 1;
+                action_tracker.embankments_since_last_event += 1;
+            }
             GameActions::Demolish(_) => {
                 action_tracker.demolished += 1;
                 action_tracker.demolished_since_last_event += 1;
@@ -397,128 +417,402 @@ fn track_actions(
         action_tracker.total += 1;
         action_tracker.total_since_last_event += 1;
 
-        let mut pop = false;
-        if let Some(event) = level_events.0.front() {
-            pop = match event.0 {
-                LevelEventType::AnyActionsComplete(x, since_last_event) => {
-                    x < if since_last_event {
-                        action_tracker.total_since_last_event
-                    } else {
-                        action_tracker.total
-                    }
-                }
-                LevelEventType::BuiltNofType(x, content, since_last_event) => {
-                    x < if since_last_event {
-                        match content {
-                            GameActionMode::Demolish => action_tracker.demolished_since_last_event,
-                            GameActionMode::DigCanal => action_tracker.canals_since_last_event,
-                            GameActionMode::ConstructLock => action_tracker.locks_since_last_event,
-                            GameActionMode::BuildAquaduct => {
-                                action_tracker.aquaducts_since_last_event
-                            }
-                            _ => 0,
+        let fired = level_events.fire(|trigger| match trigger {
+            LevelEventType::AnyActionsComplete(x, since_last_event) => Some(
+                *x < if *since_last_event {
+                    action_tracker.total_since_last_event
+                } else {
+                    action_tracker.total
+                },
+            ),
+            LevelEventType::BuiltNofType(x, content, since_last_event) => Some(
+                *x < if *since_last_event {
+                    match content {
+                        GameActionMode::Demolish => action_tracker.demolished_since_last_event,
+                        GameActionMode::DigCanal => action_tracker.canals_since_last_event,
+                        GameActionMode::ConstructLock => action_tracker.locks_since_last_event,
+                        GameActionMode::BuildAquaduct => action_tracker.aquaducts_since_last_event,
+                        GameActionMode::BuildReservoir => {
+                            action_tracker.reservoirs_since_last_event
+                        }
+                        GameActionMode::BuildPumpingStation => {
+                            action_tracker.pumping_stations_since_last_event
                         }
-                    } else {
-                        match content {
-                            GameActionMode::Demolish => action_tracker.demolished,
-                            GameActionMode::DigCanal => action_tracker.canals,
-                            GameActionMode::ConstructLock => action_tracker.locks,
-                            GameActionMode::BuildAquaduct => action_tracker.aquaducts,
-                            _ => 0,
+                        GameActionMode::BuildTunnel => action_tracker.tunnels_since_last_event,
+                        GameActionMode::DigCutting => action_tracker.cuttings_since_last_event,
+                        GameActionMode::BuildEmbankment => {
+                            action_tracker.embankments_since_last_event
                         }
+                        _ => 0,
                     }
-                }
-                _ => false,
+                } else {
+                    match content {
+                        GameActionMode::Demolish => action_tracker.demolished,
+                        GameActionMode::DigCanal => action_tracker.canals,
+                        GameActionMode::ConstructLock => action_tracker.locks,
+                        GameActionMode::BuildAquaduct => action_tracker.aquaducts,
+                        GameActionMode::BuildReservoir => action_tracker.reservoirs,
+                        GameActionMode::BuildPumpingStation => action_tracker.pumping_stations,
+                        GameActionMode::BuildTunnel => action_tracker.tunnels,
+                        GameActionMode::DigCutting => action_tracker.cuttings,
+                        GameActionMode::BuildEmbankment => action_tracker.embankments,
+                        _ => 0,
+                    }
+                },
+            ),
+            LevelEventType::ToolUsedInRegion(mode, region) => {
+                let tile = event.tile();
+                Some(event.mode() == *mode && region.contains(tile.x, tile.y))
             }
+            _ => None,
+        });
+        for event in fired {
+            info!("Reached Event {event:?}");
+            events.send(event);
         }
-        if pop {
-            if let Some(event) = level_events.0.pop_front() {
-                info!("Reached Event {event:?}");
-                events.send(event);
-                return;
+    }
+}
+/// Makes a change to each of the board's tiles at the given positions.
+fn change_tiles(
+    board: &Board,
+    tiles: &mut Query<&mut Tile>,
+    positions: impl IntoIterator<Item = (usize, usize)>,
+    change: impl Fn(&mut Tile),
+) {
+    for position in positions {
+        if let Some(entity) = board.children.get(&position) {
+            if let Ok(mut tile) = tiles.get_mut(*entity) {
+                change(&mut tile);
             }
         }
     }
 }
 
+fn set_contents(tile: &mut Tile, contents: TileContents) {
+    tile.contents = contents;
+    tile.wetness = match contents {
+        TileContents::River => Wetness::WaterSource,
+        _ => Wetness::Dry,
+    };
+}
+
 fn process_level_event(
     mut events: EventReader<LevelEvent>,
     mut tiles: Query<&mut Tile>,
+    boards: Query<&Board>,
     mut commands: Commands,
     mut action_tracker: ResMut<ActionTracker>,
     mut tools: ResMut<LevelTools>,
+    mut resources: ResMut<GameResources>,
+    mut flags: ResMut<LevelFlags>,
+    mut choices: EventReader<ChoiceMade>,
+    level: Res<Level>,
 ) {
+    let board = match boards.get_single() {
+        Ok(board) => board,
+        Err(_) => return,
+    };
+    let mut actions = vec![];
     for event in events.iter() {
         action_tracker.total_since_last_event = 0;
         action_tracker.demolished_since_last_event = 0;
         action_tracker.aquaducts_since_last_event = 0;
+        action_tracker.reservoirs_since_last_event = 0;
+        action_tracker.pumping_stations_since_last_event = 0;
+        action_tracker.tunnels_since_last_event = 0;
+        action_tracker.cuttings_since_last_event = 0;
+        action_tracker.embankments_since_last_event = 0;
         action_tracker.locks_since_last_event = 0;
         action_tracker.canals_since_last_event = 0;
+        actions.extend(event.1.iter());
+    }
+    for choice in choices.iter() {
+        resources.cost_so_far += choice.0;
+        actions.extend(choice.1.iter());
+    }
 
-        for action in event.1.iter() {
-            match action {
-                EventAction::DisplayText {
-                    text,
-                    title,
-                    continue_button,
-                } => {
-                    commands.insert_resource(CurrentDescription {
-                        text: Some(text.clone()),
-                        title: title.clone(),
-                        continue_button: continue_button.clone(),
-                    });
-                    commands.insert_resource(NextState(GameState::Description));
-                }
-                EventAction::SetNewGoal(x, y) => {
-                    for mut tile in tiles.iter_mut() {
-                        if tile.x == *x && tile.y == *y {
-                            tile.is_goal = true;
-                            break;
-                        }
-                    }
-                }
-                EventAction::AdjustCost(x, y, modifier) => {
-                    for mut tile in tiles.iter_mut() {
-                        if tile.x == *x && tile.y == *y {
-                            tile.cost_modifier = *modifier;
-                            break;
-                        }
-                    }
-                }
-                EventAction::AdjustContents(x, y, contents) => {
-                    for mut tile in tiles.iter_mut() {
-                        if tile.x == *x && tile.y == *y {
-                            tile.contents = *contents;
-                            tile.wetness = match *contents {
-                                TileContents::River => Wetness::WaterSource,
-                                _ => Wetness::Dry,
-                            };
-                            break;
-                        }
-                    }
-                }
-                EventAction::SetHeight(x, y, h) => {
-                    for mut tile in tiles.iter_mut() {
-                        if tile.x == *x && tile.y == *y {
-                            tile.z = *h;
-                            break;
-                        }
+    for action in actions {
+        match action {
+            EventAction::DisplayText {
+                text,
+                title,
+                continue_button,
+            } => {
+                commands.insert_resource(CurrentDescription {
+                    text: Some(text.clone()),
+                    title: title.clone(),
+                    continue_button: continue_button.clone(),
+                    choices: vec![],
+                });
+                commands.insert_resource(NextState(GameState::Description));
+            }
+            EventAction::Dialogue {
+                text,
+                title,
+                choices,
+            } => {
+                commands.insert_resource(CurrentDescription {
+                    text: Some(text.clone()),
+                    title: title.clone(),
+                    continue_button: None,
+                    choices: choices.clone(),
+                });
+                commands.insert_resource(NextState(GameState::Description));
+            }
+            EventAction::SetNewGoal(x, y) => {
+                change_tiles(board, &mut tiles, [(*x, *y)], |tile| tile.is_goal = true);
+            }
+            EventAction::AdjustCost(x, y, modifier) => {
+                change_tiles(board, &mut tiles, [(*x, *y)], |tile| {
+                    tile.cost_modifier = *modifier
+                });
+            }
+            EventAction::AdjustContents(x, y, contents) => {
+                change_tiles(board, &mut tiles, [(*x, *y)], |tile| {
+                    set_contents(tile, *contents)
+                });
+            }
+            EventAction::SetHeight(x, y, h) => {
+                change_tiles(board, &mut tiles, [(*x, *y)], |tile| tile.z = *h);
+            }
+            EventAction::SetNewGoalsIn(selection) => {
+                change_tiles(board, &mut tiles, selection.positions(&level), |tile| {
+                    tile.is_goal = true
+                });
+            }
+            EventAction::AdjustCostIn(selection, modifier) => {
+                change_tiles(board, &mut tiles, selection.positions(&level), |tile| {
+                    tile.cost_modifier = *modifier
+                });
+            }
+            EventAction::AdjustContentsIn(selection, contents) => {
+                change_tiles(board, &mut tiles, selection.positions(&level), |tile| {
+                    set_contents(tile, *contents)
+                });
+            }
+            EventAction::SetHeightIn(selection, h) => {
+                change_tiles(board, &mut tiles, selection.positions(&level), |tile| {
+                    tile.z = *h
+                });
+            }
+            EventAction::MakeWaterSource(selection) => {
+                change_tiles(board, &mut tiles, selection.positions(&level), |tile| {
+                    tile.wetness = Wetness::WaterSource
+                });
+            }
+            EventAction::ClearGoals => {
+                for mut tile in tiles.iter_mut() {
+                    if tile.is_goal {
+                        tile.is_goal = false;
                     }
                 }
-                EventAction::AdjustToolAccess(action_mode, action) => {
-                    info!("Setting the action mode {action_mode:?} {action:?}");
-                    match action_mode {
-                        GameActionMode::None => {}
-                        GameActionMode::DigCanal => tools.canal = *action,
-                        GameActionMode::ConstructLock => tools.lock = *action,
-                        GameActionMode::BuildAquaduct => tools.aquaduct = *action,
-                        GameActionMode::Demolish => tools.demolish = *action,
-                    }
+            }
+            EventAction::AdjustToolAccess(action_mode, action) => {
+                info!("Setting the action mode {action_mode:?} {action:?}");
+                tools.set(*action_mode, *action);
+            }
+            EventAction::SetSidebar(text) => commands.insert_resource(SidebarText(text.clone())),
+            EventAction::SetWaterCycle(cycle) => {
+                commands.insert_resource(cycle.clone());
+            }
+            EventAction::GrantFunds(funds) => {
+                if let Some(budget) = resources.budget.as_mut() {
+                    *budget += funds;
                 }
-                EventAction::SetSidebar(text) => {
-                    commands.insert_resource(SidebarText(text.clone()))
+            }
+            EventAction::WithdrawFunds(funds) => {
+                if let Some(budget) = resources.budget.as_mut() {
+                    *budget = budget.saturating_sub(*funds);
                 }
             }
+            EventAction::SetFlag(flag) => {
+                flags.0.insert(flag.clone());
+            }
+            EventAction::ClearFlag(flag) => {
+                flags.0.remove(flag);
+            }
+            EventAction::CompleteLevel => {
+                commands.insert_resource(NextState(GameState::Complete));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+    use iyes_loopless::state::NextState;
+
+    use super::{
+        check_goals_for_sucess, run_water_simulation, WaterGraph, WaterShortages,
+        WaterSimulationRuns,
+    };
+    use crate::game::{
+        board::*,
+        game_state::GameState,
+        level::{EventAction, EventOptions, LevelEvent, LevelEventType, PendingLevelEvents},
+        roads::SeveredRoads,
+        water_cycle::WaterLevels,
+    };
+
+    fn world_with(width: usize, height: usize, tiles: impl IntoIterator<Item = Tile>) -> World {
+        let mut world = World::new();
+        world.init_resource::<WaterShortages>();
+        world.init_resource::<WaterGraph>();
+        world.init_resource::<WaterLevels>();
+        world.init_resource::<WaterSimulationRuns>();
+
+        let mut board = Board {
+            width,
+            height,
+            ..Default::default()
+        };
+        for tile in tiles {
+            let position = (tile.x, tile.y);
+            let entity = world.spawn((tile, WetnessSource::None)).id();
+            board.children.insert(position, entity);
+        }
+        world.spawn(board);
+        world
+    }
+
+    fn tile(x: usize, y: usize, z: usize, contents: TileContents) -> Tile {
+        Tile {
+            x,
+            y,
+            z,
+            contents,
+            wetness: if contents == TileContents::River {
+                Wetness::WaterSource
+            } else {
+                Wetness::Dry
+            },
+            ..Default::default()
+        }
+    }
+
+    fn tile_at(world: &mut World, x: usize, y: usize) -> Mut<'_, Tile> {
+        let entity = world
+            .query::<&Board>()
+            .single(world)
+            .children
+            .get(&(x, y))
+            .copied()
+            .unwrap();
+        world.get_mut::<Tile>(entity).unwrap()
+    }
+
+    /// A river down the left edge, feeding a canal along every other row.
+    fn board_world(size: usize) -> World {
+        let tiles = (0..size).flat_map(|x| {
+            (0..size).map(move |y| {
+                let contents = match (x, y % 2) {
+                    (0, _) => TileContents::River,
+                    (_, 0) => TileContents::Canal,
+                    _ => TileContents::None,
+                };
+                tile(x, y, 0, contents)
+            })
+        });
+        world_with(size, size, tiles)
+    }
+
+    #[test]
+    fn idle_boards_skip_the_water_simulation() {
+        let mut world = board_world(20);
+        let mut stage = SystemStage::single_threaded().with_system(run_water_simulation);
+
+        stage.run(&mut world);
+        // One more run picks up the tiles that just got wet, and finds nothing left to do
+        stage.run(&mut world);
+        let far_end = tile_at(&mut world, 19, 18).wetness;
+        assert_eq!(far_end, Wetness::Wet(19));
+
+        let settled = world.resource::<WaterSimulationRuns>().runs;
+        for _ in 0..10 {
+            stage.run(&mut world);
+        }
+        assert_eq!(world.resource::<WaterSimulationRuns>().runs, settled);
+
+        tile_at(&mut world, 5, 1).contents = TileContents::Canal;
+        stage.run(&mut world);
+        assert_eq!(world.resource::<WaterSimulationRuns>().runs, settled + 1);
+    }
+
+    /// A river feeding a short canal in the top left corner, with nothing but land beyond it.
+    fn corner_world(size: usize) -> World {
+        let tiles = (0..size).flat_map(|x| {
+            (0..size).map(move |y| {
+                let contents = match (x, y) {
+                    (0, 0) => TileContents::River,
+                    (1..=4, 0) => TileContents::Canal,
+                    _ => TileContents::None,
+                };
+                tile(x, y, 0, contents)
+            })
+        });
+        world_with(size, size, tiles)
+    }
+
+    #[test]
+    fn changes_only_read_the_tiles_around_them() {
+        let touched = [20, 200].map(|size| {
+            let mut world = corner_world(size);
+            let mut stage = SystemStage::single_threaded().with_system(run_water_simulation);
+            stage.run(&mut world);
+            assert_eq!(
+                world.resource::<WaterSimulationRuns>().tiles,
+                size * size,
+                "laying out the board settles all of it"
+            );
+            // The tiles that just got wet are checked over once more
+            stage.run(&mut world);
+
+            tile_at(&mut world, 5, 0).contents = TileContents::Canal;
+            stage.run(&mut world);
+            assert_eq!(tile_at(&mut world, 5, 0).wetness, Wetness::Wet(5));
+            let graph = world.resource::<WaterGraph>();
+            assert_eq!(
+                graph.component_at(5, 0),
+                graph.component_at(0, 0),
+                "the graph is up to date without another run"
+            );
+            world.resource::<WaterSimulationRuns>().tiles
+        });
+
+        assert_eq!(touched[0], touched[1]);
+        assert!(touched[0] < 40, "read {} tiles", touched[0]);
+    }
+
+    #[test]
+    fn repeating_goal_events_keep_the_level_going() {
+        let mut goal = tile(1, 0, 0, TileContents::Canal);
+        goal.is_goal = true;
+        let mut world = world_with(2, 1, [tile(0, 0, 0, TileContents::River), goal]);
+        world.init_resource::<SeveredRoads>();
+        world.init_resource::<Events<LevelEvent>>();
+        world.insert_resource(PendingLevelEvents::new(&[LevelEvent(
+            LevelEventType::GoalReached,
+            vec![EventAction::Dialogue {
+                text: "Another load's waiting.".into(),
+                title: None,
+                choices: vec![],
+            }],
+            EventOptions {
+                repeat: true,
+                ..Default::default()
+            },
+        )]));
+        let mut stage = SystemStage::single_threaded()
+            .with_system(run_water_simulation)
+            .with_system(check_goals_for_sucess.after(run_water_simulation));
+
+        for round in 1..=2 {
+            tile_at(&mut world, 1, 0).is_goal = true;
+            stage.run(&mut world);
+            assert!(!tile_at(&mut world, 1, 0).is_goal, "round {round}");
+            assert_eq!(world.resource::<Events<LevelEvent>>().len(), round);
+            assert!(world.get_resource::<NextState<GameState>>().is_none());
         }
     }
 }
@@ -0,0 +1,234 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Write,
+};
+
+use bevy::prelude::Resource;
+
+use crate::game::board::{GridTopology, Tile, TileContents, Topology, WetnessSource};
+
+/// A wet tile, as it appears in the water graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaterNode {
+    pub x: usize,
+    pub y: usize,
+    pub level: usize,
+    pub contents: TileContents,
+    pub is_source: bool,
+}
+
+/// A single body of water - every tile in it shares water with the others.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WaterComponent {
+    pub tiles: Vec<(usize, usize)>,
+    /// The sources feeding this body of water, if any.
+    pub sources: Vec<(usize, usize)>,
+}
+
+impl WaterComponent {
+    /// The source the whole body of water is credited to - the lowest of its sources.
+    pub fn source(&self) -> WetnessSource {
+        match self.sources.iter().min() {
+            Some((x, y)) => WetnessSource::Source(*x, *y),
+            None => WetnessSource::None,
+        }
+    }
+}
+
+/// The wet tiles on a board, linked to the neighbours they share water with.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct WaterGraph {
+    nodes: HashMap<(usize, usize), WaterNode>,
+    /// The wet neighbours each wet tile shares water with.
+    edges: HashMap<(usize, usize), Vec<(usize, usize)>>,
+    /// The bodies of water - any left empty after part of the graph is replaced get reused.
+    components: Vec<WaterComponent>,
+    /// The component each wet tile belongs to.
+    membership: HashMap<(usize, usize), usize>,
+}
+
+impl WaterGraph {
+    /// Links up the given wet tiles, joining neighbours wherever `connected` says they share water.
+    pub fn new<'a, T: IntoIterator<Item = &'a Tile>>(
+        width: usize,
+        height: usize,
+        topology: GridTopology,
+        tiles: T,
+        connected: impl Fn(&Tile, &Tile) -> bool,
+    ) -> Self {
+        let tiles = tiles
+            .into_iter()
+            .filter(|tile| tile.x < width && tile.y < height)
+            .map(|tile| ((tile.x, tile.y), tile))
+            .collect::<HashMap<_, _>>();
+        let mut positions = tiles.keys().copied().collect::<Vec<_>>();
+        positions.sort_by_key(|(x, y)| (*y, *x));
+
+        let edges = tiles
+            .iter()
+            .map(|(position, tile)| {
+                let linked = topology
+                    .neighbours(tile.x, tile.y, width, height)
+                    .into_iter()
+                    .flatten()
+                    .filter(|neighbour| {
+                        matches!(tiles.get(neighbour), Some(other) if connected(tile, other))
+                    })
+                    .collect();
+                (*position, linked)
+            })
+            .collect::<HashMap<_, Vec<_>>>();
+
+        let nodes = tiles
+            .iter()
+            .map(|(position, tile)| {
+                let node = WaterNode {
+                    x: tile.x,
+                    y: tile.y,
+                    level: tile.water_level(),
+                    contents: tile.contents,
+                    is_source: tile.is_water_source(),
+                };
+                (*position, node)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut components = vec![];
+        let mut membership = HashMap::new();
+        for start in positions {
+            if membership.contains_key(&start) {
+                continue;
+            }
+            let mut component = WaterComponent::default();
+            membership.insert(start, components.len());
+            let mut queue = VecDeque::from([start]);
+            while let Some(position) = queue.pop_front() {
+                component.tiles.push(position);
+                if nodes[&position].is_source {
+                    component.sources.push(position);
+                }
+                for next in edges[&position].iter() {
+                    if !membership.contains_key(next) {
+                        membership.insert(*next, components.len());
+                        queue.push_back(*next);
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        Self {
+            nodes,
+            edges,
+            components,
+            membership,
+        }
+    }
+
+    /// Swaps the bodies of water in a region of the board for the ones in `graph`, which only
+    /// covers that region. No body of water can cross the edge of the region, as is the case for
+    /// the regions `WaterNetwork::around` finds.
+    pub fn replace_region(&mut self, region: &HashSet<(usize, usize)>, graph: &WaterGraph) {
+        for position in region.iter() {
+            if let Some(component) = self.membership.get(position).copied() {
+                for tile in std::mem::take(&mut self.components[component].tiles) {
+                    self.nodes.remove(&tile);
+                    self.edges.remove(&tile);
+                    self.membership.remove(&tile);
+                }
+                self.components[component].sources.clear();
+            }
+        }
+
+        for component in graph.components() {
+            let slot = match self.components.iter().position(|c| c.tiles.is_empty()) {
+                Some(slot) => slot,
+                None => {
+                    self.components.push(WaterComponent::default());
+                    self.components.len() - 1
+                }
+            };
+            for tile in component.tiles.iter() {
+                self.nodes.insert(*tile, graph.nodes[tile]);
+                self.edges.insert(*tile, graph.edges[tile].clone());
+                self.membership.insert(*tile, slot);
+            }
+            self.components[slot] = component.clone();
+        }
+    }
+
+    /// Every body of water on the board.
+    pub fn components(&self) -> impl Iterator<Item = &WaterComponent> {
+        self.components.iter().filter(|c| !c.tiles.is_empty())
+    }
+
+    /// The body of water the tile at (x, y) is part of - `None` if it's dry.
+    pub fn component_at(&self, x: usize, y: usize) -> Option<&WaterComponent> {
+        self.membership
+            .get(&(x, y))
+            .map(|component| &self.components[*component])
+    }
+
+    /// The fewest tiles water has to pass through to get between two wet tiles, including both ends.
+    pub fn path(&self, from: (usize, usize), to: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        if !self.nodes.contains_key(&from) || !self.nodes.contains_key(&to) {
+            return None;
+        }
+
+        let mut previous = HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(position) = queue.pop_front() {
+            if position == to {
+                let mut path = vec![position];
+                let mut current = position;
+                while let Some(next) = previous[&current] {
+                    path.push(next);
+                    current = next;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.edges[&position].iter() {
+                if !previous.contains_key(next) {
+                    previous.insert(*next, Some(position));
+                    queue.push_back(*next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Writes the graph out in GraphViz DOT format, with each body of water in its own cluster.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "graph water {\n    node [shape=box];\n".to_string();
+        for (index, component) in self.components().enumerate() {
+            let _ = writeln!(dot, "    subgraph cluster_{index} {{");
+            let _ = writeln!(dot, "        label=\"{:?}\";", component.source());
+            for (x, y) in component.tiles.iter() {
+                if let Some(node) = self.nodes.get(&(*x, *y)) {
+                    let _ = writeln!(
+                        dot,
+                        "        \"{x},{y}\" [label=\"{:?}\\n({x}, {y}) level {}\"{}];",
+                        node.contents,
+                        node.level,
+                        if node.is_source {
+                            ", shape=ellipse"
+                        } else {
+                            ""
+                        }
+                    );
+                }
+            }
+            dot.push_str("    }\n");
+        }
+        for component in self.components() {
+            for a in component.tiles.iter() {
+                for b in self.edges[a].iter().filter(|b| (b.1, b.0) > (a.1, a.0)) {
+                    let _ = writeln!(dot, "    \"{},{}\" -- \"{},{}\";", a.0, a.1, b.0, b.1);
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
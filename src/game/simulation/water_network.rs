@@ -0,0 +1,984 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+};
+
+use crate::game::board::{
+    GridTopology, Tile, TileContents, TileType, Topology, Wetness, WetnessSource,
+};
+
+use super::WaterGraph;
+
+/// How much water a lock draws from its supply each time it is worked.
+pub const LOCK_WATER_USE: usize = 1;
+
+/// How far a pumping station can lift water above the tile feeding it.
+pub const PUMP_LIFT: usize = 5;
+
+/// How many times longer it takes a boat to work through a lock than to cross open water.
+pub const LOCK_PASSAGE_COST: usize = 4;
+
+/// The settled water state for a single tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaterState {
+    pub wetness: Wetness,
+    pub source: WetnessSource,
+    pub flooded: bool,
+}
+
+impl Default for WaterState {
+    fn default() -> Self {
+        Self {
+            wetness: Wetness::Dry,
+            source: WetnessSource::None,
+            flooded: false,
+        }
+    }
+}
+
+/// A plain grid of tiles that water can be settled across in one go,
+/// without needing an ECS world.
+///
+/// It only holds the tiles it's given, so settling part of a board costs no more on a big board
+/// than on a small one.
+#[derive(Debug, Clone)]
+pub struct WaterNetwork {
+    width: usize,
+    height: usize,
+    topology: GridTopology,
+    /// The tiles, in rows from the top left - the order water is settled across them in.
+    tiles: Vec<Tile>,
+    /// Where each position's tile is in `tiles`.
+    lookup: HashMap<(usize, usize), usize>,
+}
+
+impl WaterNetwork {
+    pub fn new<'a, T: IntoIterator<Item = &'a Tile>>(
+        width: usize,
+        height: usize,
+        tiles: T,
+    ) -> Self {
+        let mut positions = HashMap::new();
+        for tile in tiles {
+            if tile.x < width && tile.y < height {
+                positions.insert((tile.x, tile.y), tile);
+            }
+        }
+        let mut tiles = positions.into_values().cloned().collect::<Vec<_>>();
+        tiles.sort_by_key(|tile| (tile.y, tile.x));
+        let lookup = tiles
+            .iter()
+            .enumerate()
+            .map(|(index, tile)| ((tile.x, tile.y), index))
+            .collect();
+        Self {
+            width,
+            height,
+            topology: GridTopology::default(),
+            tiles,
+            lookup,
+        }
+    }
+
+    /// Gathers just the tiles that changes at the given positions could affect, looking them up
+    /// as it goes, along with the ones around them needed to settle them - returning the network
+    /// and the region to settle, which water can never cross into or out of.
+    pub fn around<'a>(
+        width: usize,
+        height: usize,
+        topology: GridTopology,
+        changed: impl IntoIterator<Item = (usize, usize)>,
+        tile_at: impl Fn(usize, usize) -> Option<&'a Tile>,
+    ) -> (Self, Vec<(usize, usize)>) {
+        let neighbours = |(x, y): (usize, usize)| {
+            topology
+                .neighbours(x, y, width, height)
+                .into_iter()
+                .flatten()
+        };
+        let region = find_affected_region(changed, &tile_at, neighbours);
+
+        // Flooding reaches two tiles out - from the water, over a canal, onto the land
+        let mut gathered = region.iter().copied().collect::<HashSet<_>>();
+        let mut edge = region.clone();
+        for _ in 0..2 {
+            edge = edge
+                .into_iter()
+                .flat_map(neighbours)
+                .filter(|position| gathered.insert(*position))
+                .collect();
+        }
+        let network = Self::new(
+            width,
+            height,
+            gathered.into_iter().filter_map(|(x, y)| tile_at(x, y)),
+        )
+        .with_topology(topology);
+        (network, region)
+    }
+
+    /// Joins the tiles up the way the level's board does, rather than on a plain square grid.
+    pub fn with_topology(mut self, topology: GridTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Raises the sea by the tide, and moves the rivers up or down, before water is settled.
+    pub fn with_levels(mut self, tide: usize, river: isize) -> Self {
+        for tile in self.tiles.iter_mut() {
+            if tile.tile_type == TileType::Sea {
+                tile.water_surface = Some(tile.water_level() + tide);
+            } else if tile.contents == TileContents::River {
+                tile.water_surface = Some((tile.water_level() as isize + river).max(0) as usize);
+            }
+        }
+        self
+    }
+
+    /// Only the tiles at the given positions, so they can be settled on their own.
+    pub fn region(&self, positions: &[(usize, usize)]) -> Self {
+        Self::new(
+            self.width,
+            self.height,
+            positions.iter().filter_map(|(x, y)| self.tile(*x, *y)),
+        )
+        .with_topology(self.topology)
+    }
+
+    /// Settles the water across the given region on its own, as found by `around`.
+    ///
+    /// Open ground in the region can still be flooded by water outside it, which is taken to
+    /// be as wet as its tiles already are.
+    pub fn solve_region(&self, positions: &[(usize, usize)]) -> WaterSolution {
+        let mut solution = self.region(positions).solve();
+        let wet = solution
+            .lookup
+            .iter()
+            .filter(|(_, index)| solution.states[**index].wetness != Wetness::Dry)
+            .map(|(position, _)| *position)
+            .collect::<HashSet<_>>();
+        let is_wet = |tile: &Tile| {
+            if solution.lookup.contains_key(&(tile.x, tile.y)) {
+                wet.contains(&(tile.x, tile.y))
+            } else {
+                tile.wetness != Wetness::Dry
+            }
+        };
+        for (x, y) in positions.iter() {
+            if let (Some(land), Some(index)) = (self.tile(*x, *y), solution.lookup.get(&(*x, *y))) {
+                solution.states[*index].flooded = self.is_flooded(land, is_wet);
+            }
+        }
+        solution
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> Option<&Tile> {
+        self.lookup.get(&(x, y)).map(|index| &self.tiles[*index])
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = &Tile> {
+        self.tiles.iter()
+    }
+
+    /// Where the tile at (x, y) is kept - only ever asked for tiles in the network.
+    fn index(&self, x: usize, y: usize) -> usize {
+        self.lookup[&(x, y)]
+    }
+
+    fn neighbours<'a>(&'a self, tile: &Tile) -> impl Iterator<Item = &'a Tile> {
+        self.topology
+            .neighbours(tile.x, tile.y, self.width, self.height)
+            .into_iter()
+            .flatten()
+            .filter_map(|(x, y)| self.tile(x, y))
+    }
+
+    /// Flows water out from every source and returns the state each tile settles into.
+    ///
+    /// Wet tiles are numbered by their distance from the nearest source, and every
+    /// connected body of water shares the lowest source it is connected to.
+    pub fn solve(&self) -> WaterSolution {
+        let mut states = vec![WaterState::default(); self.tiles.len()];
+        let mut queue = VecDeque::new();
+
+        for tile in self.tiles() {
+            if tile.is_water_source() {
+                let index = self.index(tile.x, tile.y);
+                states[index].wetness = Wetness::WaterSource;
+                queue.push_back((tile, 0));
+            }
+        }
+
+        while let Some((tile, distance)) = queue.pop_front() {
+            for neighbour in self.neighbours(tile) {
+                let index = self.index(neighbour.x, neighbour.y);
+                if states[index].wetness == Wetness::Dry && receives_from(neighbour, tile) {
+                    states[index].wetness = Wetness::Wet(distance + 1);
+                    queue.push_back((neighbour, distance + 1));
+                }
+            }
+        }
+
+        let shortages = self.find_shortages(&states);
+        for shortage in shortages.iter() {
+            for (x, y) in shortage.tiles.iter() {
+                states[self.index(*x, *y)].wetness = Wetness::Dry;
+            }
+        }
+
+        self.find_floods(&mut states);
+
+        let graph =
+            self.graph_where(|tile| states[self.index(tile.x, tile.y)].wetness != Wetness::Dry);
+        for component in graph.components() {
+            let source = component.source();
+            for (x, y) in component.tiles.iter() {
+                states[self.index(*x, *y)].source = source;
+            }
+        }
+
+        WaterSolution {
+            width: self.width,
+            height: self.height,
+            lookup: self.lookup.clone(),
+            states,
+            shortages,
+            graph,
+        }
+    }
+
+    fn graph_where(&self, is_wet: impl Fn(&Tile) -> bool) -> WaterGraph {
+        WaterGraph::new(
+            self.width,
+            self.height,
+            self.topology,
+            self.tiles().filter(|tile| is_wet(tile)),
+            joins,
+        )
+    }
+
+    /// Marks the land that floods where water meets a lower canal with no lock in between,
+    /// and spills over the canal's banks.
+    fn find_floods(&self, states: &mut [WaterState]) {
+        let is_wet = |tile: &Tile| states[self.index(tile.x, tile.y)].wetness != Wetness::Dry;
+        let flooded = self
+            .tiles()
+            .filter(|land| self.is_flooded(land, is_wet))
+            .map(|land| self.index(land.x, land.y))
+            .collect::<Vec<_>>();
+        for index in flooded {
+            states[index].flooded = true;
+        }
+    }
+
+    /// Whether this open ground sits beside a canal that a higher, wet neighbour spills over.
+    fn is_flooded(&self, land: &Tile, is_wet: impl Fn(&Tile) -> bool) -> bool {
+        if !can_flood(land) {
+            return false;
+        }
+        self.neighbours(land)
+            .filter(|canal| matches!(canal.contents, TileContents::Canal | TileContents::Bridge))
+            .any(|canal| {
+                let banks = canal.z.max(canal.water_level());
+                self.neighbours(canal).any(|tile| {
+                    let level = tile.water_level();
+                    is_wet(tile) && spills(tile) && level > banks && land.z < level
+                })
+            })
+    }
+
+    /// Finds the wet stretches that only limited sources reach, and checks whether
+    /// those sources hold enough water to work every lock along them.
+    fn find_shortages(&self, states: &[WaterState]) -> Vec<WaterShortage> {
+        let mut unlimited = vec![false; self.tiles.len()];
+        let mut queue = VecDeque::new();
+        for tile in self.tiles() {
+            if tile.is_water_source() && tile.supply_capacity().is_none() {
+                unlimited[self.index(tile.x, tile.y)] = true;
+                queue.push_back(tile);
+            }
+        }
+        while let Some(tile) = queue.pop_front() {
+            for neighbour in self.neighbours(tile) {
+                let index = self.index(neighbour.x, neighbour.y);
+                if !unlimited[index]
+                    && states[index].wetness != Wetness::Dry
+                    && receives_from(neighbour, tile)
+                {
+                    unlimited[index] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        let is_limited = |tile: &Tile| {
+            let index = self.index(tile.x, tile.y);
+            !unlimited[index] && !tile.is_water_source() && states[index].wetness != Wetness::Dry
+        };
+
+        let mut shortages = vec![];
+        let mut visited = vec![false; self.tiles.len()];
+        for start in self.tiles() {
+            let start_index = self.index(start.x, start.y);
+            if visited[start_index] || !is_limited(start) {
+                continue;
+            }
+
+            visited[start_index] = true;
+            let mut tiles = vec![];
+            let mut feeding_sources = vec![];
+            let mut demand = 0;
+            let mut queue = VecDeque::from([start]);
+
+            while let Some(tile) = queue.pop_front() {
+                tiles.push((tile.x, tile.y));
+                if tile.contents == TileContents::Lock {
+                    demand += LOCK_WATER_USE;
+                }
+                for neighbour in self.neighbours(tile) {
+                    if neighbour.is_water_source() {
+                        let position = (neighbour.x, neighbour.y);
+                        if receives_from(tile, neighbour) && !feeding_sources.contains(&position) {
+                            feeding_sources.push(position);
+                        }
+                        continue;
+                    }
+                    let index = self.index(neighbour.x, neighbour.y);
+                    if !visited[index] && is_limited(neighbour) && joins(tile, neighbour) {
+                        visited[index] = true;
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+
+            let supply = feeding_sources
+                .iter()
+                .filter_map(|(x, y)| self.tile(*x, *y).and_then(|t| t.supply_capacity()))
+                .sum();
+
+            if demand > supply {
+                shortages.push(WaterShortage {
+                    supply,
+                    demand,
+                    tiles,
+                });
+            }
+        }
+        shortages
+    }
+
+    /// Finds the quickest way for a boat to get between two wet tiles, including both ends.
+    ///
+    /// Boats can only travel along wet, navigable tiles that form a single body of water,
+    /// and take longer to pass through locks.
+    pub fn route(&self, from: (usize, usize), to: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        self.tile(from.0, from.1).filter(|t| is_navigable(t))?;
+        self.tile(to.0, to.1).filter(|t| is_navigable(t))?;
+
+        let mut costs = vec![usize::MAX; self.tiles.len()];
+        let mut previous = vec![None; self.tiles.len()];
+        let mut queue = BinaryHeap::new();
+        costs[self.index(from.0, from.1)] = 0;
+        queue.push(Reverse((0, from)));
+
+        while let Some(Reverse((cost, (x, y)))) = queue.pop() {
+            if (x, y) == to {
+                let mut route = vec![to];
+                let mut current = to;
+                while let Some(next) = previous[self.index(current.0, current.1)] {
+                    route.push(next);
+                    current = next;
+                }
+                route.reverse();
+                return Some(route);
+            }
+            if cost > costs[self.index(x, y)] {
+                continue;
+            }
+            if let Some(tile) = self.tile(x, y) {
+                for neighbour in self.neighbours(tile) {
+                    if !is_navigable(neighbour) || !joins(tile, neighbour) {
+                        continue;
+                    }
+                    let index = self.index(neighbour.x, neighbour.y);
+                    let next_cost = cost + passage_cost(neighbour);
+                    if next_cost < costs[index] {
+                        costs[index] = next_cost;
+                        previous[index] = Some((x, y));
+                        queue.push(Reverse((next_cost, (neighbour.x, neighbour.y))));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Every tile whose water could be affected by changes at the given positions - the changed
+/// tiles, anything that can hold water joined to them, whatever the current water levels, and
+/// the open ground beside all of those that they could flood.
+///
+/// Water can never cross into or out of this region, so it can be settled on its own.
+fn find_affected_region<'a, N: IntoIterator<Item = (usize, usize)>>(
+    changed: impl IntoIterator<Item = (usize, usize)>,
+    tile_at: impl Fn(usize, usize) -> Option<&'a Tile>,
+    neighbours: impl Fn((usize, usize)) -> N,
+) -> Vec<(usize, usize)> {
+    let mut visited = HashSet::new();
+    let mut region = vec![];
+    let mut queue = VecDeque::new();
+    for (x, y) in changed {
+        if tile_at(x, y).is_some() {
+            if visited.insert((x, y)) {
+                region.push((x, y));
+            }
+            // A tile that stopped holding water still cuts off its neighbours
+            queue.push_back((x, y));
+        }
+    }
+
+    while let Some(position) = queue.pop_front() {
+        for (x, y) in neighbours(position) {
+            if let Some(neighbour) = tile_at(x, y) {
+                if can_hold_water(neighbour) && visited.insert((x, y)) {
+                    region.push((x, y));
+                    queue.push_back((x, y));
+                }
+            }
+        }
+    }
+
+    let banks = region
+        .iter()
+        .flat_map(|position| neighbours(*position))
+        .filter(|(x, y)| matches!(tile_at(*x, *y), Some(tile) if can_flood(tile)))
+        .collect::<Vec<_>>();
+    for position in banks {
+        if visited.insert(position) {
+            region.push(position);
+        }
+    }
+    region
+}
+
+/// Whether water on this tile is free to pour into a lower neighbour - anything but a lock or pump.
+fn spills(tile: &Tile) -> bool {
+    tile.is_water_source()
+        || matches!(
+            tile.contents,
+            TileContents::Canal
+                | TileContents::Bridge
+                | TileContents::River
+                | TileContents::Aquaduct(_)
+                | TileContents::Tunnel(_)
+        )
+}
+
+/// Whether this tile is open ground that overflowing water can spread across.
+fn can_flood(tile: &Tile) -> bool {
+    tile.tile_type != TileType::Sea
+        && matches!(tile.contents, TileContents::None | TileContents::Road)
+}
+
+/// Whether water could ever settle on this tile.
+pub fn can_hold_water(tile: &Tile) -> bool {
+    tile.is_water_source() || !matches!(tile.contents, TileContents::None | TileContents::Road)
+}
+
+/// Whether a boat can float on this tile.
+fn is_navigable(tile: &Tile) -> bool {
+    tile.wetness != Wetness::Dry
+        && matches!(
+            tile.contents,
+            TileContents::Canal
+                | TileContents::Bridge
+                | TileContents::Lock
+                | TileContents::Aquaduct(_)
+                | TileContents::Tunnel(_)
+                | TileContents::River
+                | TileContents::Reservoir
+        )
+}
+
+/// How long it takes a boat to cross a tile, relative to a plain stretch of canal.
+pub fn passage_cost(tile: &Tile) -> usize {
+    match tile.contents {
+        TileContents::Lock => LOCK_PASSAGE_COST,
+        _ => 1,
+    }
+}
+
+/// A stretch of water fed only by limited sources, whose locks need more water than it gets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaterShortage {
+    pub supply: usize,
+    pub demand: usize,
+    pub tiles: Vec<(usize, usize)>,
+}
+
+/// The settled wetness and source of every tile on a board.
+#[derive(Debug, Clone)]
+pub struct WaterSolution {
+    width: usize,
+    height: usize,
+    /// Where each settled tile's state is in `states`.
+    lookup: HashMap<(usize, usize), usize>,
+    states: Vec<WaterState>,
+    shortages: Vec<WaterShortage>,
+    graph: WaterGraph,
+}
+
+impl WaterSolution {
+    /// The state the tile at (x, y) settled into - tiles left out of the network stay dry.
+    pub fn get(&self, x: usize, y: usize) -> Option<WaterState> {
+        if x < self.width && y < self.height {
+            Some(match self.lookup.get(&(x, y)) {
+                Some(index) => self.states[*index],
+                None => WaterState::default(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The stretches of water that ran dry because their locks used more water than they were given.
+    pub fn shortages(&self) -> &[WaterShortage] {
+        &self.shortages
+    }
+
+    /// The bodies of water the board settled into.
+    pub fn graph(&self) -> &WaterGraph {
+        &self.graph
+    }
+
+    /// Whether the tiles already match this solution - meaning the layout has settled.
+    pub fn is_settled<'a, T: IntoIterator<Item = (&'a Tile, &'a WetnessSource)>>(
+        &self,
+        tiles: T,
+    ) -> bool {
+        tiles
+            .into_iter()
+            .all(|(tile, source)| match self.get(tile.x, tile.y) {
+                Some(state) => {
+                    state.wetness == tile.wetness
+                        && state.source == *source
+                        && state.flooded == tile.flooded
+                }
+                None => true,
+            })
+    }
+}
+
+/// Whether `tile` can take on water from a wet `neighbour`. Water never flows uphill.
+pub fn receives_from(tile: &Tile, neighbour: &Tile) -> bool {
+    let z = tile.water_level();
+    let nz = neighbour.water_level();
+    match tile.contents {
+        TileContents::Canal
+        | TileContents::Bridge
+        | TileContents::Aquaduct(_)
+        | TileContents::Tunnel(_) => z == nz,
+        TileContents::Lock => match neighbour.contents {
+            TileContents::Aquaduct(_) => z == nz,
+            _ => z <= nz && nz - z < 5,
+        },
+        TileContents::PumpingStation => nz <= z && z - nz < PUMP_LIFT,
+        _ => false,
+    }
+}
+
+/// Whether water can pass between two tiles next to one another.
+pub fn joins(a: &Tile, b: &Tile) -> bool {
+    connects_to(a, b) || connects_to(b, a)
+}
+
+fn connects_to(tile: &Tile, neighbour: &Tile) -> bool {
+    let z = tile.water_level();
+    let nz = neighbour.water_level();
+    if tile.is_water_source() {
+        return match neighbour.contents {
+            TileContents::River => z.abs_diff(nz) < 15,
+            _ => z == nz,
+        };
+    }
+    match tile.contents {
+        TileContents::Canal
+        | TileContents::Bridge
+        | TileContents::Aquaduct(_)
+        | TileContents::Tunnel(_) => z == nz,
+        TileContents::Lock => match neighbour.contents {
+            TileContents::Aquaduct(_) => z == nz,
+            _ => z.abs_diff(nz) < 5,
+        },
+        TileContents::PumpingStation => z.abs_diff(nz) < PUMP_LIFT,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{WaterNetwork, WaterState};
+    use crate::game::board::{GridTopology, Tile, TileContents, TileType, Wetness, WetnessSource};
+
+    fn tile(x: usize, y: usize, z: usize, contents: TileContents) -> Tile {
+        Tile {
+            x,
+            y,
+            z,
+            contents,
+            wetness: if contents == TileContents::River {
+                Wetness::WaterSource
+            } else {
+                Wetness::Dry
+            },
+            ..Default::default()
+        }
+    }
+
+    fn row(tiles: &[(usize, TileContents)]) -> WaterNetwork {
+        let tiles = tiles
+            .iter()
+            .enumerate()
+            .map(|(x, (z, contents))| tile(x, 0, *z, *contents))
+            .collect::<Vec<_>>();
+        WaterNetwork::new(tiles.len(), 1, tiles.iter())
+    }
+
+    #[test]
+    fn canal_fills_from_river_in_one_pass() {
+        let network = row(&[
+            (0, TileContents::River),
+            (0, TileContents::Canal),
+            (0, TileContents::Canal),
+            (0, TileContents::None),
+        ]);
+        let solution = network.solve();
+
+        let source = WetnessSource::Source(0, 0);
+        assert_eq!(
+            solution.get(1, 0),
+            Some(WaterState {
+                wetness: Wetness::Wet(1),
+                source,
+                flooded: false,
+            })
+        );
+        assert_eq!(
+            solution.get(2, 0),
+            Some(WaterState {
+                wetness: Wetness::Wet(2),
+                source,
+                flooded: false,
+            })
+        );
+        assert_eq!(solution.get(3, 0), Some(WaterState::default()));
+    }
+
+    #[test]
+    fn water_needs_a_lock_to_step_down() {
+        let canal = row(&[(3, TileContents::River), (1, TileContents::Canal)]).solve();
+        assert_eq!(canal.get(1, 0).map(|s| s.wetness), Some(Wetness::Dry));
+
+        let lock = row(&[(3, TileContents::River), (1, TileContents::Lock)]).solve();
+        assert_eq!(lock.get(1, 0).map(|s| s.wetness), Some(Wetness::Wet(1)));
+
+        let uphill = row(&[(1, TileContents::River), (3, TileContents::Lock)]).solve();
+        assert_eq!(uphill.get(1, 0).map(|s| s.wetness), Some(Wetness::Dry));
+    }
+
+    #[test]
+    fn aquaduct_must_match_water_level() {
+        let network = row(&[
+            (2, TileContents::River),
+            (0, TileContents::Aquaduct(2)),
+            (2, TileContents::Canal),
+            (0, TileContents::Aquaduct(1)),
+        ]);
+        let solution = network.solve();
+        assert_eq!(solution.get(1, 0).map(|s| s.wetness), Some(Wetness::Wet(1)));
+        assert_eq!(solution.get(2, 0).map(|s| s.wetness), Some(Wetness::Wet(2)));
+        assert_eq!(solution.get(3, 0).map(|s| s.wetness), Some(Wetness::Dry));
+    }
+
+    #[test]
+    fn tunnel_carries_water_under_a_hill() {
+        let network = row(&[
+            (2, TileContents::River),
+            (2, TileContents::Canal),
+            (5, TileContents::Tunnel(3)),
+            (6, TileContents::Tunnel(4)),
+            (2, TileContents::Canal),
+            (6, TileContents::Tunnel(3)),
+        ]);
+        let solution = network.solve();
+        assert_eq!(solution.get(3, 0).map(|s| s.wetness), Some(Wetness::Wet(3)));
+        assert_eq!(solution.get(4, 0).map(|s| s.wetness), Some(Wetness::Wet(4)));
+        assert_eq!(solution.get(5, 0).map(|s| s.wetness), Some(Wetness::Dry));
+    }
+
+    #[test]
+    fn high_tide_needs_a_lock() {
+        for (contents, dry_at_high_tide) in
+            [(TileContents::Canal, true), (TileContents::Lock, false)]
+        {
+            let mut tiles = [tile(0, 0, 0, TileContents::None), tile(1, 0, 0, contents)];
+            tiles[0].tile_type = TileType::Sea;
+            tiles[0].wetness = Wetness::WaterSource;
+
+            let low = WaterNetwork::new(2, 1, tiles.iter()).solve();
+            assert_eq!(low.get(1, 0).map(|s| s.wetness), Some(Wetness::Wet(1)));
+
+            let high = WaterNetwork::new(2, 1, tiles.iter())
+                .with_levels(2, 0)
+                .solve();
+            assert_eq!(
+                high.get(1, 0).map(|s| s.wetness == Wetness::Dry),
+                Some(dry_at_high_tide)
+            );
+        }
+    }
+
+    #[test]
+    fn embankments_carry_water_above_the_ground() {
+        let mut tiles = [
+            tile(0, 0, 2, TileContents::River),
+            tile(1, 0, 0, TileContents::Canal),
+            tile(2, 0, 4, TileContents::Canal),
+            tile(3, 0, 4, TileContents::Canal),
+        ];
+        let solution = WaterNetwork::new(4, 1, tiles.iter()).solve();
+        assert_eq!(solution.get(1, 0).map(|s| s.wetness), Some(Wetness::Dry));
+
+        // An embankment lifts the first canal up to the river, and a cutting drops the next ones
+        tiles[1].water_surface = Some(2);
+        tiles[2].water_surface = Some(2);
+        let solution = WaterNetwork::new(4, 1, tiles.iter()).solve();
+        assert_eq!(solution.get(1, 0).map(|s| s.wetness), Some(Wetness::Wet(1)));
+        assert_eq!(solution.get(2, 0).map(|s| s.wetness), Some(Wetness::Wet(2)));
+        assert_eq!(solution.get(3, 0).map(|s| s.wetness), Some(Wetness::Dry));
+    }
+
+    #[test]
+    fn water_spills_over_a_lower_canal() {
+        for (contents, floods) in [(TileContents::Canal, true), (TileContents::Lock, false)] {
+            let tiles = [
+                tile(0, 0, 2, TileContents::River),
+                tile(1, 0, 1, contents),
+                tile(2, 0, 1, TileContents::None),
+                tile(0, 1, 0, TileContents::None),
+                tile(1, 1, 3, TileContents::None),
+                tile(2, 1, 0, TileContents::None),
+            ];
+            let solution = WaterNetwork::new(3, 2, tiles.iter()).solve();
+            let flooded = |x, y| solution.get(x, y).map(|s| s.flooded);
+
+            assert_eq!(flooded(2, 0), Some(floods));
+            // Too high for the water to reach, and too far from the canal
+            assert_eq!(flooded(1, 1), Some(false));
+            assert_eq!(flooded(0, 1), Some(false));
+            assert_eq!(flooded(2, 1), Some(false));
+        }
+    }
+
+    #[test]
+    fn diagonal_boards_carry_water_across_corners() {
+        let tiles = [
+            tile(0, 0, 0, TileContents::River),
+            tile(1, 0, 0, TileContents::None),
+            tile(0, 1, 0, TileContents::None),
+            tile(1, 1, 0, TileContents::Canal),
+        ];
+        for (topology, wetness) in [
+            (GridTopology::Square, Wetness::Dry),
+            (GridTopology::Diagonal, Wetness::Wet(1)),
+        ] {
+            let solution = WaterNetwork::new(2, 2, tiles.iter())
+                .with_topology(topology)
+                .solve();
+            assert_eq!(solution.get(1, 1).map(|s| s.wetness), Some(wetness));
+        }
+    }
+
+    #[test]
+    fn joined_sources_share_the_lowest_source() {
+        let mut tiles = [
+            tile(0, 0, 0, TileContents::None),
+            tile(1, 0, 0, TileContents::Canal),
+            tile(2, 0, 0, TileContents::River),
+        ];
+        tiles[0].tile_type = TileType::Sea;
+        tiles[0].wetness = Wetness::WaterSource;
+        let network = WaterNetwork::new(3, 1, tiles.iter());
+        let solution = network.solve();
+
+        for x in 0..3 {
+            assert_eq!(
+                solution.get(x, 0).map(|s| s.source),
+                Some(WetnessSource::Source(0, 0))
+            );
+        }
+    }
+
+    #[test]
+    fn locks_run_a_small_source_dry() {
+        let mut tiles = [
+            tile(0, 0, 4, TileContents::River),
+            tile(1, 0, 4, TileContents::Canal),
+            tile(2, 0, 2, TileContents::Lock),
+            tile(3, 0, 0, TileContents::Lock),
+        ];
+        tiles[0].water_capacity = Some(1);
+        let solution = WaterNetwork::new(4, 1, tiles.iter()).solve();
+
+        assert_eq!(solution.shortages().len(), 1);
+        assert_eq!(solution.shortages()[0].demand, 2);
+        for x in 1..4 {
+            assert_eq!(solution.get(x, 0).map(|s| s.wetness), Some(Wetness::Dry));
+        }
+
+        tiles[0].water_capacity = Some(2);
+        let solution = WaterNetwork::new(4, 1, tiles.iter()).solve();
+        assert!(solution.shortages().is_empty());
+        assert_eq!(solution.get(3, 0).map(|s| s.wetness), Some(Wetness::Wet(3)));
+    }
+
+    #[test]
+    fn reservoir_feeds_its_own_locks() {
+        let network = row(&[
+            (4, TileContents::Reservoir),
+            (2, TileContents::Lock),
+            (0, TileContents::Lock),
+            (0, TileContents::Canal),
+        ]);
+        let solution = network.solve();
+        assert!(solution.shortages().is_empty());
+        assert_eq!(
+            solution.get(0, 0).map(|s| s.wetness),
+            Some(Wetness::WaterSource)
+        );
+        assert_eq!(solution.get(3, 0).map(|s| s.wetness), Some(Wetness::Wet(3)));
+        assert_eq!(
+            solution.get(3, 0).map(|s| s.source),
+            Some(WetnessSource::Source(0, 0))
+        );
+    }
+
+    #[test]
+    fn pumping_station_lifts_water_uphill() {
+        let network = row(&[
+            (0, TileContents::River),
+            (3, TileContents::PumpingStation),
+            (3, TileContents::Canal),
+            (9, TileContents::PumpingStation),
+        ]);
+        let solution = network.solve();
+        assert_eq!(solution.get(1, 0).map(|s| s.wetness), Some(Wetness::Wet(1)));
+        assert_eq!(solution.get(2, 0).map(|s| s.wetness), Some(Wetness::Wet(2)));
+        assert_eq!(solution.get(3, 0).map(|s| s.wetness), Some(Wetness::Dry));
+    }
+
+    #[test]
+    fn solution_reports_when_settled() {
+        let network = row(&[(0, TileContents::River), (0, TileContents::Canal)]);
+        let solution = network.solve();
+        let mut tiles = network.tiles().cloned().collect::<Vec<_>>();
+        let mut sources = [WetnessSource::Source(0, 0), WetnessSource::None];
+        assert!(!solution.is_settled(tiles.iter().zip(sources.iter())));
+
+        tiles[1].wetness = Wetness::Wet(1);
+        sources[1] = WetnessSource::Source(0, 0);
+        assert!(solution.is_settled(tiles.iter().zip(sources.iter())));
+    }
+
+    #[test]
+    fn graph_splits_the_board_into_bodies_of_water() {
+        let network = row(&[
+            (0, TileContents::River),
+            (0, TileContents::Canal),
+            (0, TileContents::Canal),
+            (0, TileContents::None),
+            (0, TileContents::Canal),
+            (1, TileContents::River),
+        ]);
+        let solution = network.solve();
+        let graph = solution.graph();
+
+        assert_eq!(graph.components().count(), 2);
+        assert_eq!(
+            graph.component_at(2, 0).map(|c| c.source()),
+            Some(WetnessSource::Source(0, 0))
+        );
+        assert_eq!(graph.component_at(3, 0), None);
+        assert_eq!(
+            graph.path((0, 0), (2, 0)),
+            Some(vec![(0, 0), (1, 0), (2, 0)])
+        );
+        assert_eq!(graph.path((0, 0), (5, 0)), None);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("graph water {"));
+        assert!(dot.contains("\"1,0\" -- \"2,0\";"));
+        assert!(!dot.contains("\"3,0\""));
+    }
+
+    #[test]
+    fn changes_only_reach_joined_water() {
+        let network = row(&[
+            (0, TileContents::River),
+            (0, TileContents::Canal),
+            (0, TileContents::None),
+            (0, TileContents::Canal),
+            (3, TileContents::Lock),
+            (0, TileContents::None),
+            (0, TileContents::Canal),
+        ]);
+
+        let (_, mut region) =
+            WaterNetwork::around(7, 1, GridTopology::default(), [(2, 0)], |x, y| {
+                network.tile(x, y)
+            });
+        region.sort();
+        assert_eq!(region, vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)]);
+
+        let solution = network.region(&region).solve();
+        assert_eq!(solution.get(1, 0).map(|s| s.wetness), Some(Wetness::Wet(1)));
+        assert_eq!(solution.get(3, 0).map(|s| s.wetness), Some(Wetness::Dry));
+        assert_eq!(solution.get(6, 0), Some(WaterState::default()));
+    }
+
+    #[test]
+    fn boats_prefer_open_water_to_locks() {
+        let mut tiles = vec![];
+        for x in 0..4 {
+            for y in 0..3 {
+                let contents = match (x, y) {
+                    (1 | 2, 1) => TileContents::None,
+                    (1 | 2, 0) => TileContents::Lock,
+                    _ => TileContents::Canal,
+                };
+                let mut tile = tile(x, y, 0, contents);
+                if contents != TileContents::None {
+                    tile.wetness = Wetness::Wet(1);
+                }
+                tiles.push(tile);
+            }
+        }
+        let network = WaterNetwork::new(4, 3, tiles.iter());
+
+        assert_eq!(
+            network.route((0, 0), (3, 0)),
+            Some(vec![
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (1, 2),
+                (2, 2),
+                (3, 2),
+                (3, 1),
+                (3, 0)
+            ])
+        );
+        assert_eq!(network.route((0, 0), (1, 0)).map(|r| r.len()), Some(2));
+        assert_eq!(network.route((0, 0), (1, 1)), None);
+    }
+}
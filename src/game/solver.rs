@@ -0,0 +1,568 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+};
+
+use super::{
+    board::{Tile, TileContents, Wetness},
+    level::{DialogueChoice, EventAction, Level, LevelEventType, PendingLevelEvents},
+    route::{Placement, RouteMap},
+};
+
+/// A way the planner found to complete a level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutePlan {
+    pub cost: usize,
+    /// Everything to build, in the order it's needed - each set of goals is joined up before the
+    /// goals the level sets after it.
+    pub placements: Vec<Placement>,
+}
+
+/// Why the planner couldn't complete a level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// A goal no route reaches.
+    Unreachable {
+        /// The `GoalReached` event waiting on the goal, if it's not one of the level's last goals.
+        event: Option<usize>,
+        goal: (usize, usize),
+    },
+    /// A source that can run dry, which the planner can't account for.
+    LimitedSupply((usize, usize)),
+    /// None of the ways through the level's events ever complete it.
+    NeverCompletes,
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanError::Unreachable {
+                event,
+                goal: (x, y),
+            } => {
+                write!(f, "No route reaches the goal at ({x}, {y})")?;
+                if let Some(event) = event {
+                    write!(f, ", so event {event} can't be reached")?;
+                }
+                Ok(())
+            }
+            PlanError::LimitedSupply((x, y)) => write!(
+                f,
+                "The source at ({x}, {y}) can run dry, so no route plan can be trusted"
+            ),
+            PlanError::NeverCompletes => write!(f, "No way through the level's events ends it"),
+        }
+    }
+}
+
+/// Goals up to this many are joined up by the cheapest network of routes there is - past that,
+/// the search takes too long, so the nearest goal is always joined up next instead.
+const MAX_TREE_GOALS: usize = 6;
+
+/// Plans canals, locks, aquaducts and bridges that complete the level, following its events the
+/// way the game fires them as they set new goals.
+///
+/// Nothing else is ever planned - no reservoirs, pumping stations, tunnels or earthworks - and
+/// every source is taken to have all the water the locks need, so levels with a source that can
+/// run dry are refused rather than given a plan that might run dry in the game.
+///
+/// Each time the level sets goals, they're joined up by the cheapest network of routes from
+/// what's been built so far, branching wherever that's cheaper - so with no more than
+/// `MAX_TREE_GOALS` goals at a time, the cost is the cheapest way to complete the level with
+/// those tools, goal by goal. With more, goals are joined up one at a time, nearest first, and
+/// the cost is only an upper bound. Every plan is checked by settling the water over it the way
+/// the game does, so each set of goals ends up wet and joined to a source.
+///
+/// Triggers the solver can't follow - actions, time, boats - are taken as met straight away.
+/// Every choice a dialogue offers is tried, and the cheapest kept.
+pub fn plan_routes(level: &Level) -> Result<RoutePlan, PlanError> {
+    let mut playthroughs = vec![Playthrough {
+        map: RouteMap::new(level),
+        goals: goals(level),
+        events: PendingLevelEvents::new(&level.events),
+        flags: HashSet::new(),
+        actions: VecDeque::new(),
+        complete: false,
+        plan: RoutePlan {
+            cost: 0,
+            placements: vec![],
+        },
+    }];
+
+    let mut best: Option<RoutePlan> = None;
+    let mut error = None;
+    while let Some(mut playthrough) = playthroughs.pop() {
+        match playthrough.play(level) {
+            Ok(None) => {
+                if !matches!(&best, Some(best) if best.cost <= playthrough.plan.cost) {
+                    best = Some(playthrough.plan);
+                }
+            }
+            Ok(Some(choices)) => {
+                for choice in choices {
+                    let mut branch = playthrough.clone();
+                    branch.plan.cost += choice.cost;
+                    for action in choice.actions.into_iter().rev() {
+                        branch.actions.push_front(action);
+                    }
+                    playthroughs.push(branch);
+                }
+            }
+            Err(e) => error = Some(e),
+        }
+    }
+    best.ok_or(error.unwrap_or(PlanError::NeverCompletes))
+}
+
+/// One way through the level, as far as the solver has followed it.
+#[derive(Clone)]
+struct Playthrough {
+    map: RouteMap,
+    goals: Vec<(usize, usize)>,
+    events: PendingLevelEvents,
+    flags: HashSet<String>,
+    /// The actions of events that have fired, still to take.
+    actions: VecDeque<EventAction>,
+    complete: bool,
+    plan: RoutePlan,
+}
+
+impl Playthrough {
+    /// Plays on until the level's complete, or there's a dialogue to answer - returning its
+    /// choices, with the actions after it left waiting.
+    fn play(&mut self, level: &Level) -> Result<Option<Vec<DialogueChoice>>, PlanError> {
+        // Repeating events could keep setting each other off, so they only get so many goes
+        let mut rounds = 0;
+        loop {
+            while let Some(action) = self.actions.pop_front() {
+                match action {
+                    EventAction::Dialogue { choices, .. } if !choices.is_empty() => {
+                        return Ok(Some(choices));
+                    }
+                    EventAction::CompleteLevel => self.complete = true,
+                    EventAction::SetFlag(flag) => {
+                        self.flags.insert(flag);
+                    }
+                    EventAction::ClearFlag(flag) => {
+                        self.flags.remove(&flag);
+                    }
+                    action => apply(level, &mut self.map, &mut self.goals, &action),
+                }
+            }
+            if self.complete {
+                return Ok(None);
+            }
+
+            if rounds <= level.events.len() {
+                rounds += 1;
+                let (flags, cost) = (&self.flags, self.plan.cost);
+                let fired = self.events.fire(|trigger| match trigger {
+                    LevelEventType::GoalReached => None,
+                    LevelEventType::FlagSet(flag) => Some(flags.contains(flag)),
+                    LevelEventType::CostAbove(above) => Some(cost > *above),
+                    trigger if trigger.is_moment() => Some(rounds == 1),
+                    _ => Some(true),
+                });
+                if !fired.is_empty() {
+                    self.actions
+                        .extend(fired.into_iter().flat_map(|event| event.1));
+                    continue;
+                }
+            }
+
+            if self.goals.is_empty() {
+                return Ok(None);
+            }
+            // Locks could run a limited source dry, and the routes don't account for that
+            let limited = self.map.sources().into_iter().find(|(x, y)| {
+                matches!(self.map.tile(*x, *y), Some(tile) if tile.supply_capacity().is_some())
+            });
+            if let Some(source) = limited {
+                return Err(PlanError::LimitedSupply(source));
+            }
+            let event = self.events.waiting_on(&LevelEventType::GoalReached);
+            join_goals(&mut self.map, &self.goals, &mut self.plan)
+                .map_err(|goal| PlanError::Unreachable { event, goal })?;
+            self.goals.clear();
+            rounds = 0;
+
+            let fired = self.events.fire(|trigger| match trigger {
+                LevelEventType::GoalReached => Some(true),
+                _ => None,
+            });
+            // Just as in the game, the level carries on whenever a goal event fires - it's up to
+            // that event to set more goals or complete the level
+            self.complete = fired.is_empty();
+            self.actions
+                .extend(fired.into_iter().flat_map(|event| event.1));
+        }
+    }
+}
+
+fn goals(level: &Level) -> Vec<(usize, usize)> {
+    let mut goals = vec![];
+    for (x, column) in level.tiles.iter().enumerate() {
+        for (y, tile) in column.iter().enumerate() {
+            if tile.is_goal {
+                goals.push((x, y));
+            }
+        }
+    }
+    goals
+}
+
+fn apply(level: &Level, map: &mut RouteMap, goals: &mut Vec<(usize, usize)>, action: &EventAction) {
+    match action {
+        EventAction::SetNewGoal(x, y) => goals.push((*x, *y)),
+        EventAction::SetNewGoalsIn(selection) => goals.extend(selection.positions(level)),
+        EventAction::ClearGoals => goals.clear(),
+        EventAction::AdjustCost(x, y, modifier) => {
+            change_tiles(map, [(*x, *y)], |tile| tile.cost_modifier = *modifier)
+        }
+        EventAction::AdjustCostIn(selection, modifier) => {
+            change_tiles(map, selection.positions(level), |tile| {
+                tile.cost_modifier = *modifier
+            })
+        }
+        EventAction::AdjustContents(x, y, contents) => {
+            change_tiles(map, [(*x, *y)], |tile| set_contents(tile, *contents))
+        }
+        EventAction::AdjustContentsIn(selection, contents) => {
+            change_tiles(map, selection.positions(level), |tile| {
+                set_contents(tile, *contents)
+            })
+        }
+        EventAction::SetHeight(x, y, height) => {
+            change_tiles(map, [(*x, *y)], |tile| tile.z = *height)
+        }
+        EventAction::SetHeightIn(selection, height) => {
+            change_tiles(map, selection.positions(level), |tile| tile.z = *height)
+        }
+        EventAction::MakeWaterSource(selection) => {
+            change_tiles(map, selection.positions(level), |tile| {
+                tile.wetness = Wetness::WaterSource
+            })
+        }
+        EventAction::AdjustToolAccess(mode, available) => map.tools_mut().set(*mode, *available),
+        _ => {}
+    }
+}
+
+fn change_tiles(
+    map: &mut RouteMap,
+    positions: impl IntoIterator<Item = (usize, usize)>,
+    change: impl Fn(&mut Tile),
+) {
+    for (x, y) in positions {
+        if let Some(tile) = map.tile_mut(x, y) {
+            change(tile);
+        }
+    }
+}
+
+fn set_contents(tile: &mut Tile, contents: TileContents) {
+    tile.contents = contents;
+    tile.wetness = match contents {
+        TileContents::River => Wetness::WaterSource,
+        _ => Wetness::Dry,
+    };
+}
+
+/// Joins every goal up to the same body of water as cheaply as it can, building the routes into
+/// the map. Fails with the first goal it can't reach from any of them.
+fn join_goals(
+    map: &mut RouteMap,
+    goals: &[(usize, usize)],
+    plan: &mut RoutePlan,
+) -> Result<(), (usize, usize)> {
+    if goals.is_empty() {
+        return Ok(());
+    }
+
+    let mut bodies: Vec<Vec<(usize, usize)>> = vec![];
+    for source in map.sources() {
+        if !bodies.iter().any(|body| body.contains(&source)) {
+            bodies.push(map.body(source));
+        }
+    }
+
+    if goals.len() <= MAX_TREE_GOALS {
+        if let Some(tree) = map.cheapest_tree(&bodies.concat(), goals) {
+            let mut planned = map.clone();
+            planned.build(&tree.placements);
+            if unfilled(&planned, goals).is_none() {
+                *map = planned;
+                plan.cost += tree.cost;
+                plan.placements.extend(tree.placements);
+                return Ok(());
+            }
+        }
+    }
+
+    let mut best: Option<(usize, Vec<Placement>)> = None;
+    let mut unreachable = goals[0];
+    for body in bodies.iter() {
+        match join_nearest_first(map, body, goals) {
+            Ok((cost, placements)) => {
+                if !matches!(&best, Some((best, _)) if *best <= cost) {
+                    best = Some((cost, placements));
+                }
+            }
+            Err(goal) => unreachable = goal,
+        }
+    }
+
+    let (cost, placements) = best.ok_or(unreachable)?;
+    let mut planned = map.clone();
+    planned.build(&placements);
+    if let Some(goal) = unfilled(&planned, goals) {
+        return Err(goal);
+    }
+    *map = planned;
+    plan.cost += cost;
+    plan.placements.extend(placements);
+    Ok(())
+}
+
+/// The first goal the water doesn't reach once it's settled across the map, the way the game
+/// checks them - every goal has to be wet, and part of the same body of water as a source.
+fn unfilled(map: &RouteMap, goals: &[(usize, usize)]) -> Option<(usize, usize)> {
+    let water = map.water();
+    let first = water.graph().component_at(goals[0].0, goals[0].1);
+    goals.iter().copied().find(|(x, y)| {
+        let component = water.graph().component_at(*x, *y);
+        !matches!(component, Some(component) if !component.sources.is_empty()) || component != first
+    })
+}
+
+/// Joins the goals up one after another, each from the water and the routes built so far.
+fn join_in_order(
+    map: &RouteMap,
+    body: &[(usize, usize)],
+    goals: impl Iterator<Item = (usize, usize)>,
+) -> Result<(usize, Vec<Placement>), (usize, usize)> {
+    let mut map = map.clone();
+    let mut connected = body.to_vec();
+    let mut cost = 0;
+    let mut placements = vec![];
+    for goal in goals {
+        let route = map.cheapest_route(&connected, goal).ok_or(goal)?;
+        map.build(&route.placements);
+        connected.extend(route.placements.iter().map(|p| (p.x, p.y)));
+        cost += route.cost;
+        placements.extend(route.placements);
+    }
+    Ok((cost, placements))
+}
+
+/// Joins up whichever goal is cheapest to reach next, until they're all joined.
+fn join_nearest_first(
+    map: &RouteMap,
+    body: &[(usize, usize)],
+    goals: &[(usize, usize)],
+) -> Result<(usize, Vec<Placement>), (usize, usize)> {
+    let mut order = vec![];
+    let mut remaining = goals.to_vec();
+    let mut planned = map.clone();
+    let mut connected = body.to_vec();
+    while !remaining.is_empty() {
+        let mut routes = vec![];
+        for goal in remaining.iter() {
+            routes.push(planned.cheapest_route(&connected, *goal).ok_or(*goal)?);
+        }
+        let nearest = routes
+            .into_iter()
+            .enumerate()
+            .min_by_key(|(_, route)| route.cost);
+        if let Some((index, route)) = nearest {
+            planned.build(&route.placements);
+            connected.extend(route.placements.iter().map(|p| (p.x, p.y)));
+            order.push(remaining.remove(index));
+        }
+    }
+    join_in_order(map, body, order.into_iter())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{plan_routes, PlanError};
+    use crate::game::{
+        board::{TileContents, TileCostModifier},
+        level::{
+            DialogueChoice, EventAction, EventOptions, Level, LevelEvent, LevelEventType, TileInfo,
+            TileSelection,
+        },
+        level_loader::load_level,
+    };
+
+    /// A river at the left end of a strip of flat land.
+    fn strip(width: usize) -> Level {
+        let mut level: Level = serde_json::from_value(serde_json::json!({
+            "tiles": (0..width)
+                .map(|_| vec![TileInfo { height: 1, ..Default::default() }])
+                .collect::<Vec<_>>(),
+            "title": null,
+            "initial_description": null,
+            "sidebar_text": null,
+            "width": width,
+            "height": 1
+        }))
+        .unwrap();
+        level.tiles[0][0].contents = TileContents::River;
+        level
+    }
+
+    /// Flat land laid out as rows of characters - `~` for the river, `#` blocked, `m` costing
+    /// double, and `G` or `H` for goals on plain or double cost ground.
+    fn grid(rows: &[&str]) -> Level {
+        let mut level = strip(rows[0].len());
+        level.height = rows.len();
+        level.tiles = (0..level.width)
+            .map(|x| {
+                rows.iter()
+                    .map(|row| {
+                        let symbol = row.as_bytes()[x];
+                        TileInfo {
+                            height: 1,
+                            contents: match symbol {
+                                b'~' => TileContents::River,
+                                _ => TileContents::None,
+                            },
+                            is_goal: matches!(symbol, b'G' | b'H'),
+                            cost_modifier: match symbol {
+                                b'#' => TileCostModifier::Blocked,
+                                b'm' | b'H' => TileCostModifier::Multiplier,
+                                _ => TileCostModifier::None,
+                            },
+                            ..Default::default()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        level
+    }
+
+    #[test]
+    fn goals_branch_off_the_cheapest_shared_route() {
+        // Joining the goals up one at a time costs 36, whichever goes first
+        let level = grid(&["#H.#.", ".m##.", "#.~m.", ".mmmG", "#..G#"]);
+        let plan = plan_routes(&level).unwrap();
+        assert_eq!(plan.cost, 33);
+        assert_eq!(plan.placements.len(), 7);
+    }
+
+    #[test]
+    fn goals_share_the_routes_between_them() {
+        let mut level = strip(5);
+        level.tiles[2][0].is_goal = true;
+        level.tiles[4][0].is_goal = true;
+
+        let plan = plan_routes(&level).unwrap();
+        assert_eq!(plan.placements.len(), 4);
+        assert_eq!(plan.cost, 4 * 3);
+    }
+
+    #[test]
+    fn goal_chains_are_followed_to_the_end() {
+        let mut level = strip(5);
+        level.tiles[1][0].is_goal = true;
+        level.tiles[3][0].cost_modifier = TileCostModifier::Blocked;
+        level.events = vec![LevelEvent(
+            LevelEventType::GoalReached,
+            vec![EventAction::SetNewGoal(4, 0)],
+            Default::default(),
+        )];
+        assert_eq!(
+            plan_routes(&level).unwrap_err(),
+            PlanError::Unreachable {
+                event: None,
+                goal: (4, 0)
+            }
+        );
+
+        level.events[0]
+            .1
+            .push(EventAction::AdjustCost(3, 0, TileCostModifier::None));
+        assert_eq!(plan_routes(&level).unwrap().cost, 4 * 3);
+    }
+
+    #[test]
+    fn flags_and_armed_events_are_followed() {
+        let mut level = strip(5);
+        level.tiles[1][0].is_goal = true;
+        level.events = vec![
+            LevelEvent(
+                LevelEventType::GoalReached,
+                vec![EventAction::SetFlag("wharf".into())],
+                Default::default(),
+            ),
+            LevelEvent(
+                LevelEventType::FlagSet("wharf".into()),
+                vec![EventAction::SetNewGoal(4, 0)],
+                EventOptions {
+                    armed: true,
+                    ..Default::default()
+                },
+            ),
+        ];
+        assert_eq!(plan_routes(&level).unwrap().cost, 4 * 3);
+    }
+
+    #[test]
+    fn the_cheapest_dialogue_choice_is_taken() {
+        let mut level = strip(5);
+        level.tiles[4][0].is_goal = true;
+        let choice = |cost, actions| DialogueChoice {
+            label: String::new(),
+            cost,
+            actions,
+        };
+        level.events = vec![LevelEvent(
+            LevelEventType::TimeElapsed(0),
+            vec![EventAction::Dialogue {
+                text: String::new(),
+                title: None,
+                choices: vec![
+                    choice(0, vec![]),
+                    choice(
+                        1,
+                        vec![EventAction::MakeWaterSource(TileSelection::List(vec![(
+                            3, 0,
+                        )]))],
+                    ),
+                ],
+            }],
+            Default::default(),
+        )];
+        assert_eq!(plan_routes(&level).unwrap().cost, 1 + 3);
+    }
+
+    #[test]
+    fn limited_sources_are_refused() {
+        let mut level = strip(5);
+        level.tiles[4][0].is_goal = true;
+        level.tiles[0][0].water_capacity = Some(2);
+        assert_eq!(
+            plan_routes(&level).unwrap_err(),
+            PlanError::LimitedSupply((0, 0))
+        );
+    }
+
+    #[test]
+    fn shipped_levels_can_be_completed_at_par() {
+        let levels = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/levels"))
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.path());
+        for path in levels {
+            let file = path.to_string_lossy();
+            let level = load_level(&file, &std::fs::read(&path).unwrap()).unwrap();
+            // The planner settles the water over every set of goals it joins up, so it only
+            // comes back with a plan that fills them all
+            let plan = plan_routes(&level).unwrap_or_else(|e| panic!("{file}: {e}"));
+            // Shipped levels only have the tools the planner uses, so their par is its plan
+            assert_eq!(level.par, Some(plan.cost), "{file}");
+        }
+    }
+}
@@ -9,6 +9,7 @@ use crate::{assets::CanalManiaAssets, ui::*};
 use super::{
     board::TileEvent,
     game_state::{GameActionMode, GameState},
+    simulation::{WaterGraph, WaterShortages},
 };
 
 pub struct TileHoverUi;
@@ -61,6 +62,8 @@ fn update_tile_hover_ui(
     mut tooltip_text: Query<(&mut GameText, &UiId<HoverUiId>)>,
     mut coin_icon: Query<(&mut Style, &GameIcon, &UiId<HoverUiId>)>,
     operation: Res<CurrentState<GameActionMode>>,
+    shortages: Res<WaterShortages>,
+    graph: Res<WaterGraph>,
 ) {
     if let (Ok(camera), Ok((mut root, _))) = (cameras.get_single(), tooltip_root.get_single_mut()) {
         for event in events.iter() {
@@ -70,9 +73,21 @@ fn update_tile_hover_ui(
 
                     let cost = match operation.0 {
                         GameActionMode::None => None,
-                        GameActionMode::DigCanal => Some(tile.get_dig_cost()),
+                        GameActionMode::DigCanal => Some(tile.get_canal_cost()),
                         GameActionMode::ConstructLock => Some(tile.get_lock_cost()),
                         GameActionMode::BuildAquaduct => Some(tile.get_aquaduct_cost()),
+                        GameActionMode::BuildReservoir => Some(tile.get_reservoir_cost()),
+                        GameActionMode::BuildPumpingStation => {
+                            Some(tile.get_pumping_station_cost())
+                        }
+                        GameActionMode::BuildTunnel => {
+                            Some(tile.get_tunnel_cost(match tile.contents {
+                                super::board::TileContents::Tunnel(d) => d + 1,
+                                _ => 1,
+                            }))
+                        }
+                        GameActionMode::DigCutting => Some(tile.get_cutting_cost()),
+                        GameActionMode::BuildEmbankment => Some(tile.get_embankment_cost()),
                         GameActionMode::Demolish => Some(tile.get_demolish_cost()),
                     };
 
@@ -92,6 +107,12 @@ fn update_tile_hover_ui(
                             super::board::TileContents::Lock => "A Lock on ",
                             super::board::TileContents::Aquaduct(_) => "An Aquaduct on ",
                             super::board::TileContents::River => "A River on ",
+                            super::board::TileContents::Reservoir => "A Reservoir on ",
+                            super::board::TileContents::PumpingStation => {
+                                "A Pumping Station on "
+                            }
+                            super::board::TileContents::Tunnel(_) => "A Tunnel under ",
+                            super::board::TileContents::Bridge => "A Bridged Canal on ",
                         },
                         tile_type,
                         match tile.wetness {
@@ -100,6 +121,60 @@ fn update_tile_hover_ui(
                             super::board::Wetness::Wet(_) => "Running Water",
                         }
                     );
+                    let secondary_text = match (tile.is_water_source(), tile.supply_capacity()) {
+                        (true, Some(capacity)) => {
+                            format!("{secondary_text}\nEnough water to work {capacity} locks")
+                        }
+                        _ => secondary_text,
+                    };
+                    let secondary_text = if tile.flooded {
+                        format!(
+                            "{secondary_text}\nFlooded - the owners were paid {} in compensation",
+                            tile.get_flood_compensation()
+                        )
+                    } else {
+                        secondary_text
+                    };
+                    let secondary_text = match tile.water_surface {
+                        Some(surface) if surface > tile.z => format!(
+                            "{secondary_text}\nOn an embankment {} Meters High",
+                            (surface - tile.z) * 20
+                        ),
+                        Some(surface) if surface < tile.z => format!(
+                            "{secondary_text}\nIn a cutting {} Meters Deep",
+                            (tile.z - surface) * 20
+                        ),
+                        _ => secondary_text,
+                    };
+                    let secondary_text = match tile.earthworks {
+                        0 => secondary_text,
+                        e if e > 0 => format!(
+                            "{secondary_text}\nThe ground was built up {} Meters",
+                            e * 20
+                        ),
+                        e => format!(
+                            "{secondary_text}\nThe ground was cut down {} Meters",
+                            -e * 20
+                        ),
+                    };
+                    let upstream = graph
+                        .component_at(tile.x, tile.y)
+                        .and_then(|component| component.sources.iter().min())
+                        .and_then(|source| graph.path((tile.x, tile.y), *source));
+                    let secondary_text = match upstream {
+                        Some(path) if path.len() > 1 => format!(
+                            "{secondary_text}\n{} tiles of waterway from its source",
+                            path.len() - 1
+                        ),
+                        _ => secondary_text,
+                    };
+                    let secondary_text = match shortages.at(tile.x, tile.y) {
+                        Some(shortage) => format!(
+                            "{secondary_text}\nRun dry - the locks here need {} water, but only get {}",
+                            shortage.demand, shortage.supply
+                        ),
+                        None => secondary_text,
+                    };
                     #[cfg(feature = "dev")]
                     let secondary_text = format!("{secondary_text}\n{},{}", tile.x, tile.y);
 
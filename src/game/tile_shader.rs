@@ -28,6 +28,7 @@ pub struct InkSettings {
     /// Params: world_darkening, vertex_color_strength, parchment_low_mix, parchment_high_mix
     pub added_params: Vec4,
     pub world_offset: Vec4,
+    /// Size: board_width, odd_row_shift, board_height, blocked_strength
     pub size: Vec4,
     pub blocked_color: Color,
     pub cost_color: Color,
@@ -61,4 +62,8 @@ pub struct TileMaterial {
     #[texture(1)]
     #[sampler(2)]
     pub info_map: Handle<Image>,
+    /// Kept apart from the info map, so it doesn't blend with the wetness along canal banks
+    #[texture(3)]
+    #[sampler(4)]
+    pub flood_map: Handle<Image>,
 }
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::IntoConditionalSystem;
+
+use super::{
+    board::*,
+    boats::{traffic_running, BoatTraffic},
+    game_state::GameResources,
+};
+
+/// What every boat pays to use the canal, whatever it carries.
+const BASE_TOLL: usize = 1;
+/// What a boat pays on top for each kind of goods one end makes and the other end wants.
+const TRADE_TOLL: usize = 3;
+/// How far back tolls count towards the current income, in seconds.
+const INCOME_WINDOW: f32 = 60.;
+
+pub struct TradePlugin;
+
+impl Plugin for TradePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TollLedger>()
+            .add_system(collect_tolls.run_if(traffic_running));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goods {
+    Food,
+    Wares,
+}
+
+/// The land around a port - farms grow food and want wares, towns make wares and want food.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Market {
+    pub farms: bool,
+    pub town: bool,
+}
+
+impl Market {
+    pub fn from_tiles<'a, T: IntoIterator<Item = &'a Tile>>(tiles: T) -> Self {
+        let mut market = Self::default();
+        for tile in tiles {
+            match tile.tile_type {
+                TileType::Farm => market.farms = true,
+                TileType::City => market.town = true,
+                _ => {}
+            }
+        }
+        market
+    }
+
+    pub fn produces(&self, goods: Goods) -> bool {
+        match goods {
+            Goods::Food => self.farms,
+            Goods::Wares => self.town,
+        }
+    }
+
+    pub fn demands(&self, goods: Goods) -> bool {
+        match goods {
+            Goods::Food => self.town,
+            Goods::Wares => self.farms,
+        }
+    }
+
+    /// The toll for carrying a load from this market to another.
+    pub fn toll_to(&self, other: &Market) -> usize {
+        let trade = [Goods::Food, Goods::Wares]
+            .into_iter()
+            .filter(|goods| self.produces(*goods) && other.demands(*goods))
+            .count();
+        BASE_TOLL + trade * TRADE_TOLL
+    }
+}
+
+/// Every toll the canal has collected, and when.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct TollLedger {
+    tolls: Vec<(f32, usize)>,
+}
+
+impl TollLedger {
+    /// How much the canal has earned over the last minute.
+    pub fn income_per_minute(&self, now: f32) -> usize {
+        let recent: usize = self
+            .tolls
+            .iter()
+            .filter(|(time, _)| now - time <= INCOME_WINDOW)
+            .map(|(_, toll)| toll)
+            .sum();
+        (recent as f32 * 60. / INCOME_WINDOW) as usize
+    }
+
+    /// How many more minutes it'll take for the canal to earn back what it cost at the current income.
+    pub fn payback_minutes(&self, resources: &GameResources, now: f32) -> Option<f32> {
+        let outstanding = -resources.profit();
+        if outstanding <= 0 {
+            return Some(0.);
+        }
+        match self.income_per_minute(now) {
+            0 => None,
+            income => Some(outstanding as f32 / income as f32),
+        }
+    }
+}
+
+fn collect_tolls(
+    traffic: Res<BoatTraffic>,
+    tiles: Query<&Tile>,
+    boards: Query<&Board>,
+    mut ledger: ResMut<TollLedger>,
+    mut resources: ResMut<GameResources>,
+) {
+    if !traffic.is_changed() {
+        return;
+    }
+    if traffic.deliveries.len() < ledger.tolls.len() {
+        *ledger = TollLedger::default();
+    }
+    if let Ok(board) = boards.get_single() {
+        let market = |(x, y): (usize, usize)| {
+            Market::from_tiles(
+                std::iter::once(Some((x, y)))
+                    .chain(board.neighbour_ids(x, y))
+                    .flatten()
+                    .filter_map(|p| board.children.get(&p))
+                    .filter_map(|e| tiles.get(*e).ok()),
+            )
+        };
+        for delivery in traffic.deliveries.iter().skip(ledger.tolls.len()) {
+            let toll = market(delivery.from).toll_to(&market(delivery.to));
+            resources.revenue += toll;
+            ledger.tolls.push((delivery.arrived_at, toll));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Market;
+
+    #[test]
+    fn trade_between_farms_and_towns_pays_more() {
+        let farm = Market {
+            farms: true,
+            town: false,
+        };
+        let town = Market {
+            farms: false,
+            town: true,
+        };
+        let wharf = Market::default();
+
+        assert_eq!(wharf.toll_to(&town), 1);
+        assert_eq!(farm.toll_to(&farm), 1);
+        assert_eq!(farm.toll_to(&town), 4);
+        assert_eq!(town.toll_to(&farm), 4);
+    }
+}
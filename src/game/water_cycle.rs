@@ -0,0 +1,234 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::IntoConditionalSystem;
+use serde::{Deserialize, Serialize};
+
+use super::game_state::GameState;
+
+pub struct WaterCyclePlugin;
+
+impl Plugin for WaterCyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaterCycle>()
+            .init_resource::<WaterLevels>()
+            .add_system(
+                advance_water_cycle
+                    .run_in_state(GameState::InGame)
+                    .before("water_simulation"),
+            );
+    }
+}
+
+/// How the sea and rivers rise and fall over the course of a level.
+#[derive(
+    Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize, Reflect, FromReflect,
+)]
+#[serde(default)]
+pub struct WaterCycle {
+    /// How far the sea rises between low and high tide.
+    pub tide_range: usize,
+    /// How many seconds it takes to go from one low tide to the next.
+    pub tide_period: f32,
+    /// Whether the rivers rise and fall with the tide, like the Hull does.
+    pub tidal_rivers: bool,
+    /// How far rivers rise in the wet season, and fall in the dry season.
+    pub season_range: usize,
+    /// How many seconds a full year of seasons lasts.
+    pub season_period: f32,
+}
+
+impl WaterCycle {
+    pub fn is_static(&self) -> bool {
+        self.tide_range == 0 && self.season_range == 0
+    }
+
+    pub fn levels_at(&self, elapsed: f32) -> WaterLevels {
+        let tide = if self.tide_period > 0. {
+            let phase = (1. - (TAU * elapsed / self.tide_period).cos()) / 2.;
+            (phase * self.tide_range as f32).round() as usize
+        } else {
+            0
+        };
+        let season = if self.season_period > 0. {
+            let phase = (TAU * elapsed / self.season_period).sin();
+            (phase * self.season_range as f32).round() as isize
+        } else {
+            0
+        };
+        WaterLevels {
+            tide,
+            river: season + if self.tidal_rivers { tide as isize } else { 0 },
+        }
+    }
+}
+
+/// How far the sea and rivers currently sit from their usual level.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WaterLevels {
+    pub tide: usize,
+    pub river: isize,
+}
+
+fn advance_water_cycle(
+    time: Res<Time>,
+    cycle: Res<WaterCycle>,
+    mut levels: ResMut<WaterLevels>,
+    mut elapsed: Local<f32>,
+) {
+    if cycle.is_changed() {
+        *elapsed = 0.;
+    }
+    *elapsed += time.delta_seconds();
+    let next = cycle.levels_at(*elapsed);
+    if *levels != next {
+        *levels = next;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use bevy::prelude::*;
+
+    use super::{advance_water_cycle, WaterCycle, WaterLevels};
+    use crate::game::{
+        board::*,
+        flooding::{pay_flood_compensation, Flooding},
+        game_state::GameResources,
+        level::Level,
+        simulation::{run_water_simulation, WaterGraph, WaterShortages, WaterSimulationRuns},
+    };
+
+    fn levels(tide: usize, river: isize) -> WaterLevels {
+        WaterLevels { tide, river }
+    }
+
+    #[test]
+    fn tides_run_from_low_to_high_and_back() {
+        let cycle = WaterCycle {
+            tide_range: 4,
+            tide_period: 20.,
+            tidal_rivers: true,
+            ..Default::default()
+        };
+        assert_eq!(cycle.levels_at(0.), levels(0, 0));
+        assert_eq!(cycle.levels_at(5.), levels(2, 2));
+        assert_eq!(cycle.levels_at(10.), levels(4, 4));
+        assert_eq!(cycle.levels_at(20.), levels(0, 0));
+        assert_eq!(cycle.levels_at(30.), levels(4, 4));
+    }
+
+    #[test]
+    fn seasons_raise_and_lower_the_rivers() {
+        let cycle = WaterCycle {
+            season_range: 2,
+            season_period: 40.,
+            ..Default::default()
+        };
+        assert_eq!(cycle.levels_at(0.), levels(0, 0));
+        assert_eq!(cycle.levels_at(10.), levels(0, 2));
+        assert_eq!(cycle.levels_at(30.), levels(0, -2));
+        assert_eq!(cycle.levels_at(40.), levels(0, 0));
+    }
+
+    #[test]
+    fn static_cycles_never_move() {
+        let cycle = WaterCycle::default();
+        assert!(cycle.is_static());
+        for elapsed in [0., 7.5, 1000.] {
+            assert_eq!(cycle.levels_at(elapsed), levels(0, 0));
+        }
+    }
+
+    /// A tidal river, with a canal dug straight off it and no lock, and a field beside the canal.
+    fn tidal_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(WaterCycle {
+            tide_range: 2,
+            tide_period: 20.,
+            tidal_rivers: true,
+            ..Default::default()
+        });
+        world.init_resource::<WaterLevels>();
+        world.init_resource::<Time>();
+        world.init_resource::<WaterShortages>();
+        world.init_resource::<WaterGraph>();
+        world.init_resource::<WaterSimulationRuns>();
+        world.init_resource::<GameResources>();
+        world.init_resource::<Flooding>();
+        world.insert_resource(
+            serde_json::from_value::<Level>(serde_json::json!({
+                "tiles": [], "title": null, "initial_description": null, "sidebar_text": null,
+                "width": 3, "height": 1
+            }))
+            .unwrap(),
+        );
+
+        let mut board = Board {
+            width: 3,
+            height: 1,
+            ..Default::default()
+        };
+        for (x, contents) in [TileContents::River, TileContents::Canal, TileContents::None]
+            .into_iter()
+            .enumerate()
+        {
+            let tile = Tile {
+                x,
+                z: 1,
+                contents,
+                wetness: if contents == TileContents::River {
+                    Wetness::WaterSource
+                } else {
+                    Wetness::Dry
+                },
+                ..Default::default()
+            };
+            let entity = world.spawn((tile, WetnessSource::None)).id();
+            board.children.insert((x, 0), entity);
+        }
+        world.spawn(board);
+        world
+    }
+
+    #[test]
+    fn tides_flood_unlocked_canals_but_only_pay_out_once() {
+        let mut world = tidal_world();
+        let mut stages = [
+            SystemStage::single_threaded().with_system(advance_water_cycle),
+            SystemStage::single_threaded().with_system(run_water_simulation),
+            SystemStage::single_threaded().with_system(pay_flood_compensation),
+        ];
+        let start = Instant::now();
+        world.resource_mut::<Time>().update_with_instant(start);
+        let mut elapsed = Duration::ZERO;
+        let mut play_until = |world: &mut World, seconds: u64| {
+            while elapsed < Duration::from_secs(seconds) {
+                elapsed += Duration::from_millis(500);
+                world
+                    .resource_mut::<Time>()
+                    .update_with_instant(start + elapsed);
+                for stage in stages.iter_mut() {
+                    stage.run(world);
+                }
+            }
+            world
+                .query::<&Tile>()
+                .iter(world)
+                .find(|tile| tile.x == 2)
+                .map(|tile| tile.flooded)
+        };
+
+        assert_eq!(play_until(&mut world, 1), Some(false));
+        assert_eq!(play_until(&mut world, 10), Some(true));
+        let compensation = world.resource::<Flooding>().compensation;
+        assert!(compensation > 0);
+
+        assert_eq!(play_until(&mut world, 20), Some(false));
+        assert_eq!(play_until(&mut world, 30), Some(true));
+        assert_eq!(world.resource::<Flooding>().compensation, compensation);
+        assert_eq!(world.resource::<GameResources>().cost_so_far, compensation);
+    }
+}
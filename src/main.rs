@@ -11,7 +11,7 @@ mod ui;
 use assets::CanalManiaAssets;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
-use bevy_common_assets::{json::JsonAssetPlugin, yaml::YamlAssetPlugin};
+use bevy_common_assets::yaml::YamlAssetPlugin;
 
 use bevy_mod_picking::PickingCameraBundle;
 
@@ -20,7 +20,9 @@ use choose_level::ChooseLevelPlugin;
 use credits::CreditsPlugin;
 use custom_picking_plugin::CustomPickingPlugin;
 use game::{
-    level::{Level, LevelList},
+    compact_level::CompactLevelLoader,
+    level::LevelList,
+    level_loader::{LevelFile, LevelLoader},
     GamePlugin,
 };
 use iyes_loopless::prelude::*;
@@ -37,6 +39,19 @@ use ui::GameUiPlugin;
 fn main() {
     #[cfg(target_arch = "wasm32")]
     console_error_panic_hook::set_once();
+
+    #[cfg(not(target_family = "wasm"))]
+    {
+        let args = std::env::args().collect::<Vec<_>>();
+        if args.get(1).map(String::as_str) == Some("import") {
+            if let Err(e) = game::level_import::run_command(&args[2..]) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+
     let mut app = App::new();
 
     app.insert_resource(Msaa { samples: 4 })
@@ -59,7 +74,9 @@ fn main() {
         .add_plugin(OrbitCameraPlugin {
             override_input_system: true,
         })
-        .add_plugin(JsonAssetPlugin::<Level>::new(&["lvl.json"]))
+        .add_asset::<LevelFile>()
+        .init_asset_loader::<LevelLoader>()
+        .init_asset_loader::<CompactLevelLoader>()
         .add_plugin(YamlAssetPlugin::<LevelList>::new(&["levels.yml"]));
 
     app.insert_resource(ClearColor(Color::hex("e7d2a4").unwrap_or_default()))